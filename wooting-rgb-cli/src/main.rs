@@ -0,0 +1,372 @@
+use std::{
+    error::Error,
+    mem,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, sleep},
+    time::Duration,
+};
+
+use clap::{builder::PossibleValuesParser, value_parser, Parser, Subcommand, ValueEnum};
+#[cfg(target_os = "linux")]
+use wooting_rgb::udev;
+use wooting_rgb::{
+    animation::{Animation, PlaybackOptions},
+    effect,
+    image::{self, ScaleMode},
+    openrgb::{self, OpenRgbServer},
+    parse_color,
+    preview::TerminalPreview,
+    recording::Recording,
+    Frame, IntoMatrixRowColumn, Key, RgbDevice, RgbKeyboard, SharedRgbKeyboard,
+};
+
+/// Control the lighting of Wooting keyboards.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Index of the device to control, as shown by `list`.
+    #[arg(short, long, global = true)]
+    device: Option<u8>,
+    /// Preview in the terminal instead of sending colors to a keyboard.
+    #[arg(long = "virtual", global = true)]
+    virtual_device: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List the connected devices.
+    List,
+    /// Show information about the selected device.
+    Info,
+    /// Set a single key to a color, such as `wooting-rgb set Escape red`.
+    Set {
+        /// Name of the key, such as `A`, `Esc` or `LeftShift`.
+        key: Key,
+        /// Hex (`#ff8000`), comma separated (`255,128,0`) or named (`orange`) color.
+        #[arg(value_parser = parse_color)]
+        color: (u8, u8, u8),
+    },
+    /// Set every key to a color.
+    Fill {
+        /// Hex (`#ff8000`), comma separated (`255,128,0`) or named (`orange`) color.
+        #[arg(value_parser = parse_color)]
+        color: (u8, u8, u8),
+    },
+    /// Restore the colors that were originally on the keyboard.
+    Reset,
+    /// Run a lighting effect until interrupted.
+    Effect {
+        /// Which effect to run.
+        #[arg(value_parser = PossibleValuesParser::new(effect::NAMES))]
+        name: String,
+        /// Color of the effect, for effects that use one.
+        #[arg(short, long, value_parser = parse_color, default_value = "white")]
+        color: (u8, u8, u8),
+        /// Seconds one cycle of the effect takes.
+        #[arg(short, long, value_parser = parse_seconds)]
+        period: Option<Duration>,
+        /// Frames per second.
+        #[arg(long, default_value_t = 30, value_parser = value_parser!(u32).range(1..))]
+        fps: u32,
+        /// Stop after this many seconds.
+        #[arg(long, value_parser = parse_seconds)]
+        duration: Option<Duration>,
+    },
+    /// Show an image on the keyboard. Animated GIFs are played.
+    Image {
+        /// Path to a PNG, JPEG or GIF file.
+        file: PathBuf,
+        /// How the image is scaled onto the keyboard.
+        #[arg(short, long, value_enum, default_value_t = Mode::Fill)]
+        mode: Mode,
+        /// How many times to play an animated GIF, or `0` to play it until interrupted.
+        #[arg(long, default_value_t = 0)]
+        loops: u32,
+        /// Playback speed multiplier for animated GIFs.
+        #[arg(long, default_value_t = 1.0, value_parser = parse_speed)]
+        speed: f32,
+    },
+    /// Let OpenRGB clients control the keyboard until interrupted.
+    Openrgb {
+        /// Address to listen on. Use `0.0.0.0` to accept clients from other machines.
+        #[arg(long, default_value = "127.0.0.1")]
+        address: String,
+        /// Port to listen on.
+        #[arg(short, long, default_value_t = openrgb::DEFAULT_PORT)]
+        port: u16,
+    },
+    /// Replay a recording.
+    Play {
+        /// Path to the recording.
+        recording: PathBuf,
+        /// Playback speed multiplier.
+        #[arg(long, default_value_t = 1.0, value_parser = parse_speed)]
+        speed: f32,
+    },
+    /// Print the udev rules that let users access Wooting devices without root.
+    #[cfg(target_os = "linux")]
+    UdevRules {
+        /// Write the rules to `--path` instead, which usually needs root.
+        #[arg(long)]
+        install: bool,
+        /// Where to install the rules.
+        #[arg(long, default_value = udev::RULES_PATH)]
+        path: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Mode {
+    Fit,
+    Fill,
+    Stretch,
+}
+
+impl From<Mode> for ScaleMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Fit => ScaleMode::Fit,
+            Mode::Fill => ScaleMode::Fill,
+            Mode::Stretch => ScaleMode::Stretch,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let Cli {
+        device,
+        virtual_device,
+        command,
+    } = cli;
+    match command {
+        Command::List => {
+            let count = wooting_rgb::device_count();
+            if count == 0 {
+                println!("No Wooting devices connected");
+            }
+            for index in 0..count {
+                wooting_rgb::select_device(index);
+                match wooting_rgb::device_info() {
+                    Ok(info) => println!("{index}: {}", info.model),
+                    Err(error) => println!("{index}: {error}"),
+                }
+            }
+            Ok(())
+        }
+        Command::Info => {
+            if virtual_device {
+                return Err("There is no device information in virtual mode".into());
+            }
+            select(device)?;
+            let info = wooting_rgb::device_info()?;
+            println!("Model:   {}", info.model);
+            println!("Type:    {:?}", info.device_type);
+            println!("Layout:  {:?}", info.layout);
+            println!(
+                "Matrix:  {} rows, {} columns",
+                info.max_rows, info.max_columns
+            );
+            Ok(())
+        }
+        Command::Set { key, color } => {
+            let mut device = open(device, virtual_device)?;
+            let (red, green, blue) = color;
+            if !device.direct_set_key_matrix(key.get_matrix_row_and_column(), red, green, blue) {
+                return Err("Unable to set key".into());
+            }
+            keep(device);
+            Ok(())
+        }
+        Command::Fill { color } => {
+            let mut device = open(device, virtual_device)?;
+            device.array_set_frame(&Frame::filled(color));
+            if !device.array_update() {
+                return Err("Unable to update keyboard".into());
+            }
+            keep(device);
+            Ok(())
+        }
+        Command::Reset => {
+            let mut device = open(device, virtual_device)?;
+            if !device.reset_all() {
+                return Err("Unable to reset keyboard".into());
+            }
+            Ok(())
+        }
+        Command::Effect {
+            name,
+            color,
+            period,
+            fps,
+            duration,
+        } => {
+            let mut effect = effect::from_name(&name, color, period)?;
+            let interrupted = interrupted()?;
+            let mut device = open(device, virtual_device)?;
+            let updated = effect::run(effect.as_mut(), device.as_mut(), fps, |elapsed| {
+                !interrupted.load(Ordering::Relaxed) && duration.is_none_or(|d| elapsed < d)
+            });
+            if !updated {
+                return Err("Unable to update keyboard".into());
+            }
+            Ok(())
+        }
+        Command::Image {
+            file,
+            mode,
+            loops,
+            speed,
+        } => {
+            let is_gif = file
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+            if !is_gif {
+                let frame = image::open(&file, mode.into())?;
+                let mut device = open(device, virtual_device)?;
+                device.array_set_frame(&frame);
+                if !device.array_update() {
+                    return Err("Unable to update keyboard".into());
+                }
+                keep(device);
+                return Ok(());
+            }
+
+            let animation = Animation::open_gif(&file, mode.into())?;
+            let options = PlaybackOptions {
+                loops: (loops > 0).then_some(loops),
+                speed,
+                ping_pong: false,
+            };
+            let interrupted = interrupted()?;
+            let mut device = open(device, virtual_device)?;
+            animation.play_while(device.as_mut(), &options, || {
+                !interrupted.load(Ordering::Relaxed)
+            })?;
+            Ok(())
+        }
+        Command::Openrgb { address, port } => {
+            let interrupted = interrupted()?;
+            if virtual_device {
+                let mut preview = TerminalPreview::stdout();
+                preview.set_live(true);
+                serve_openrgb((address.as_str(), port), preview, &interrupted)
+            } else {
+                select(device)?;
+                wooting_rgb::check_connection()?;
+                let keyboard = SharedRgbKeyboard::open()?;
+                serve_openrgb((address.as_str(), port), keyboard.clone(), &interrupted)?;
+                // The server still holds a handle to the keyboard, so restore its colors
+                // through this one.
+                keyboard.reset_all();
+                Ok(())
+            }
+        }
+        Command::Play { recording, speed } => {
+            let recording = Recording::open(&recording)?;
+            let interrupted = interrupted()?;
+            let mut device = open(device, virtual_device)?;
+            recording.replay_while(device.as_mut(), speed, || {
+                !interrupted.load(Ordering::Relaxed)
+            })?;
+            Ok(())
+        }
+        #[cfg(target_os = "linux")]
+        Command::UdevRules { install, path } => {
+            if !install {
+                print!("{}", udev::rules());
+                return Ok(());
+            }
+            udev::install_rules(&path)
+                .map_err(|error| format!("Unable to write {}: {error}", path.display()))?;
+            println!("Installed the udev rules to {}", path.display());
+            println!("Apply them with: udevadm control --reload-rules && udevadm trigger");
+            Ok(())
+        }
+    }
+}
+
+/// Parse a playback speed multiplier, which must be a number greater than zero.
+fn parse_speed(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err("must be a number greater than zero".into()),
+    }
+}
+
+/// Parse a number of seconds greater than zero.
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse().map(Duration::try_from_secs_f32) {
+        Ok(Ok(duration)) if !duration.is_zero() => Ok(duration),
+        _ => Err("must be a number of seconds greater than zero".into()),
+    }
+}
+
+/// Select the device to control, if one was chosen.
+fn select(device: Option<u8>) -> Result<(), Box<dyn Error>> {
+    match device {
+        Some(index) if !wooting_rgb::select_device(index) => {
+            Err(format!("There is no device {index}, see `wooting-rgb list`").into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Open the device to send colors to, either a keyboard or a live terminal preview.
+fn open(device: Option<u8>, virtual_device: bool) -> Result<Box<dyn RgbDevice>, Box<dyn Error>> {
+    if virtual_device {
+        let mut preview = TerminalPreview::stdout();
+        preview.set_live(true);
+        return Ok(Box::new(preview));
+    }
+
+    select(device)?;
+    wooting_rgb::check_connection()?;
+    Ok(Box::new(RgbKeyboard::open()?))
+}
+
+/// Serve a device to OpenRGB clients until interrupted.
+fn serve_openrgb<D: RgbDevice + Send + 'static>(
+    address: (&str, u16),
+    device: D,
+    interrupted: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+    let server = OpenRgbServer::bind(address, device)?;
+    eprintln!("Listening for OpenRGB clients on {}", server.local_addr()?);
+    thread::spawn(move || server.run());
+    while !interrupted.load(Ordering::Relaxed) {
+        sleep(Duration::from_millis(100));
+    }
+    Ok(())
+}
+
+/// Exit without resetting the keyboard, so the colors stay on it until `wooting-rgb reset`.
+fn keep(device: Box<dyn RgbDevice>) {
+    mem::forget(device);
+}
+
+/// Return a flag that is set once the user presses Ctrl+C, so the keyboard can be reset
+/// before exiting.
+fn interrupted() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed))?;
+    Ok(interrupted)
+}
@@ -0,0 +1,340 @@
+//! A daemon that owns the keyboard and lets several applications light it at once. Clients
+//! connect to a Unix socket and send JSON requests, one per line (see [`protocol`]). Each client
+//! draws on named layers, and where layers overlap the one with the highest priority wins.
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use wooting_rgb::{effect, preview::TerminalPreview, Frame, Key, RgbDevice, RgbKeyboard};
+
+use crate::{
+    protocol::{Request, Response},
+    state::{ClientId, Content, State},
+};
+
+mod protocol;
+mod state;
+
+/// The longest request line accepted, in bytes. Even a layer that names every key is far
+/// shorter, so a client that sends more is dropped instead of buffered without end.
+const MAX_LINE: u64 = 64 * 1024;
+
+/// Share the lighting of a Wooting keyboard between applications.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    /// Path of the control socket. Defaults to `wooting-rgb.sock` in `$XDG_RUNTIME_DIR`, which
+    /// must be set unless a path is given.
+    #[arg(short, long)]
+    socket: Option<PathBuf>,
+    /// Index of the device to control.
+    #[arg(short, long)]
+    device: Option<u8>,
+    /// Preview in the terminal instead of sending colors to a keyboard.
+    #[arg(long = "virtual")]
+    virtual_device: bool,
+    /// How many times per second the layers are combined and sent to the keyboard.
+    #[arg(long, default_value_t = 30)]
+    fps: u32,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    // A shared directory like `/tmp` would let other users take the socket over.
+    let socket = match args.socket {
+        Some(socket) => socket,
+        None => env::var_os("XDG_RUNTIME_DIR")
+            .map(|directory| PathBuf::from(directory).join("wooting-rgb.sock"))
+            .ok_or("XDG_RUNTIME_DIR is not set, choose a socket path with --socket")?,
+    };
+
+    let mut device = open(args.device, args.virtual_device)?;
+
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(format!(
+            "Another daemon is already listening on {}",
+            socket.display()
+        )
+        .into());
+    }
+    // Left over from a daemon that didn't exit cleanly.
+    let _ = fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    eprintln!("Listening on {}", socket.display());
+
+    let running = Arc::new(AtomicBool::new(true));
+    let flag = running.clone();
+    ctrlc::set_handler(move || flag.store(false, Ordering::Relaxed))?;
+
+    let state = Arc::new(Mutex::new(State::default()));
+    let clients = state.clone();
+    thread::spawn(move || {
+        for (id, stream) in (1..).zip(listener.incoming()) {
+            let Ok(stream) = stream else { continue };
+            let state = clients.clone();
+            thread::spawn(move || serve(id, stream, &state));
+        }
+    });
+
+    let interval = Duration::from_secs(1) / args.fps.max(1);
+    let mut shown = None;
+    while running.load(Ordering::Relaxed) {
+        let frame = state.lock().unwrap().compose(Instant::now());
+        if shown != Some(frame) {
+            device.array_set_frame(&frame);
+            // Try again next frame if the keyboard is unplugged for a moment.
+            shown = device.array_update().then_some(frame);
+        }
+        sleep(interval);
+    }
+
+    // Dropping the keyboard restores its original colors.
+    drop(device);
+    fs::remove_file(&socket)?;
+    Ok(())
+}
+
+/// Open the device to send colors to, either a keyboard or a live terminal preview.
+fn open(device: Option<u8>, virtual_device: bool) -> Result<Box<dyn RgbDevice>, Box<dyn Error>> {
+    if virtual_device {
+        let mut preview = TerminalPreview::stdout();
+        preview.set_live(true);
+        return Ok(Box::new(preview));
+    }
+
+    if let Some(index) = device {
+        if !wooting_rgb::select_device(index) {
+            return Err(format!("There is no device {index}").into());
+        }
+    }
+    wooting_rgb::check_connection()?;
+    Ok(Box::new(RgbKeyboard::open()?))
+}
+
+/// Answer the requests of one client until it disconnects or sends a line longer than
+/// [`MAX_LINE`], then remove its layers.
+fn serve(id: ClientId, stream: UnixStream, state: &Mutex<State>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        // Read one byte past the limit, to tell a line that is too long from one that fits.
+        match (&mut reader)
+            .take(MAX_LINE + 1)
+            .read_until(b'\n', &mut line)
+        {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let too_long = line.len() as u64 > MAX_LINE && !line.ends_with(b"\n");
+        let response = if too_long {
+            Response::error(format!("Requests can't be longer than {MAX_LINE} bytes"))
+        } else {
+            let Ok(line) = std::str::from_utf8(&line) else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(request) => handle(id, request, state),
+                Err(error) => Response::error(error),
+            }
+        };
+        let Ok(mut response) = serde_json::to_string(&response) else {
+            break;
+        };
+        response.push('\n');
+        if writer.write_all(response.as_bytes()).is_err() || too_long {
+            break;
+        }
+    }
+    state.lock().unwrap().remove_client(id);
+}
+
+fn handle(id: ClientId, request: Request, state: &Mutex<State>) -> Response {
+    match try_handle(id, request, state) {
+        Ok(response) => response,
+        Err(error) => Response::error(error),
+    }
+}
+
+fn try_handle(
+    id: ClientId,
+    request: Request,
+    state: &Mutex<State>,
+) -> Result<Response, Box<dyn Error>> {
+    match request {
+        Request::Ping => {}
+        Request::SetLayer {
+            layer,
+            priority,
+            fill,
+            keys,
+        } => {
+            let mut colors = match fill {
+                Some(color) => Frame::filled(color.parse()?).to_array(),
+                None => Vec::new(),
+            };
+            for (key, color) in keys {
+                colors.push((key.parse()?, color.parse()?));
+            }
+            let content = Content::Keys(colors);
+            state
+                .lock()
+                .unwrap()
+                .set_layer(id, layer, priority, content);
+        }
+        Request::StartEffect {
+            layer,
+            priority,
+            effect,
+            color,
+            period,
+        } => {
+            let color = match color {
+                Some(color) => color.parse()?,
+                None => (255, 255, 255),
+            };
+            let period = period
+                .map(|period| effect::period_from_secs(period.into()))
+                .transpose()?;
+            let content = Content::Effect(effect::from_name(&effect, color, period)?);
+            state
+                .lock()
+                .unwrap()
+                .set_layer(id, layer, priority, content);
+        }
+        Request::ClearLayer { layer } => {
+            if !state.lock().unwrap().clear_layer(id, &layer) {
+                return Err(format!("There is no layer named {layer}").into());
+            }
+        }
+        Request::Flash {
+            keys,
+            color,
+            priority,
+            count,
+            interval_ms,
+        } => {
+            let keys = if keys.is_empty() {
+                Key::ALL.to_vec()
+            } else {
+                keys.iter()
+                    .map(|key| key.parse())
+                    .collect::<Result<_, _>>()?
+            };
+            let content = Content::Flash {
+                keys,
+                color: color.parse()?,
+                interval: Duration::from_millis(interval_ms.get()),
+                count: count.get(),
+            };
+            state.lock().unwrap().flash(id, priority, content);
+        }
+        Request::ListLayers => {
+            return Ok(Response {
+                layers: Some(state.lock().unwrap().layers(id)),
+                ..Response::ok()
+            });
+        }
+    }
+    Ok(Response::ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Shutdown;
+
+    use super::*;
+
+    /// Serve one client on a thread of its own, returning the client's end of the connection.
+    fn connect(state: &Arc<Mutex<State>>) -> (UnixStream, thread::JoinHandle<()>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        let state = state.clone();
+        (client, thread::spawn(move || serve(1, server, &state)))
+    }
+
+    #[test]
+    fn requests() {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (mut client, served) = connect(&state);
+        let mut responses = BufReader::new(client.try_clone().unwrap()).lines();
+        let mut request = |request: &str| {
+            writeln!(client, "{request}").unwrap();
+            responses.next().unwrap().unwrap()
+        };
+
+        assert_eq!(request(r#"{"command": "ping"}"#), r#"{"ok":true}"#);
+        // Blank lines are skipped rather than answered.
+        assert_eq!(
+            request(concat!(
+                "\n",
+                r#"{"command": "set_layer", "layer": "a", "keys": {"Esc": "red"}}"#
+            )),
+            r#"{"ok":true}"#
+        );
+        assert_eq!(
+            request(r#"{"command": "list_layers"}"#),
+            r#"{"ok":true,"layers":[{"name":"a","priority":0,"kind":"keys","own":true}]}"#
+        );
+        assert!(request("not json").starts_with(r#"{"ok":false,"error":"#));
+        assert_eq!(
+            request(r#"{"command": "clear_layer", "layer": "b"}"#),
+            r#"{"ok":false,"error":"There is no layer named b"}"#
+        );
+
+        // The layers of a client go away with it.
+        client.shutdown(Shutdown::Both).unwrap();
+        served.join().unwrap();
+        assert!(state.lock().unwrap().layers(1).is_empty());
+    }
+
+    #[test]
+    fn long_line() {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (mut client, served) = connect(&state);
+        let mut responses = BufReader::new(client.try_clone().unwrap()).lines();
+
+        // A line that only just fits is still answered.
+        let padding = " ".repeat(MAX_LINE as usize - 20);
+        writeln!(client, r#"{padding}{{"command":"ping"}}"#).unwrap();
+        assert_eq!(responses.next().unwrap().unwrap(), r#"{"ok":true}"#);
+
+        // The client is dropped once a line runs past the limit, without waiting for its end.
+        client
+            .write_all(&vec![b' '; MAX_LINE as usize + 1])
+            .unwrap();
+        let response = responses.next().unwrap().unwrap();
+        assert!(response.starts_with(r#"{"ok":false,"error":"#));
+        served.join().unwrap();
+        assert!(responses.next().is_none());
+    }
+}
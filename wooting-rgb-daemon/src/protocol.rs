@@ -0,0 +1,199 @@
+//! The JSON-lines protocol spoken over the control socket. Every line a client sends is one
+//! [`Request`] and the daemon answers every request with one [`Response`] line.
+//!
+//! ```text
+//! > {"command": "set_layer", "layer": "build", "priority": 10, "keys": {"Esc": "red"}}
+//! < {"ok": true}
+//! > {"command": "flash", "keys": ["F1"], "color": [255, 255, 0], "count": 3}
+//! < {"ok": true}
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    num::{NonZeroU32, NonZeroU64},
+};
+
+use serde::{Deserialize, Serialize};
+use wooting_rgb::Color;
+
+fn default_flash_priority() -> i32 {
+    100
+}
+
+fn default_flash_count() -> NonZeroU32 {
+    NonZeroU32::MIN
+}
+
+fn default_flash_interval() -> NonZeroU64 {
+    NonZeroU64::new(250).unwrap()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Check that the daemon is running.
+    Ping,
+    /// Create or replace a layer of static colors. `fill` colors every key, after which the
+    /// colors in `keys` are applied, keyed by key name.
+    SetLayer {
+        layer: String,
+        #[serde(default)]
+        priority: i32,
+        #[serde(default)]
+        fill: Option<Color>,
+        #[serde(default)]
+        keys: BTreeMap<String, Color>,
+    },
+    /// Create or replace a layer that runs one of the built-in effects.
+    StartEffect {
+        layer: String,
+        #[serde(default)]
+        priority: i32,
+        effect: String,
+        #[serde(default)]
+        color: Option<Color>,
+        /// Seconds one cycle of the effect takes.
+        #[serde(default)]
+        period: Option<f32>,
+    },
+    /// Remove a layer the client created.
+    ClearLayer { layer: String },
+    /// Blink keys, or the whole keyboard if no keys are given, `count` times. `count` and
+    /// `interval_ms` must be greater than zero.
+    Flash {
+        #[serde(default)]
+        keys: Vec<String>,
+        color: Color,
+        #[serde(default = "default_flash_priority")]
+        priority: i32,
+        #[serde(default = "default_flash_count")]
+        count: NonZeroU32,
+        /// Milliseconds the keys stay on, and then off, per blink.
+        #[serde(default = "default_flash_interval")]
+        interval_ms: NonZeroU64,
+    },
+    /// List the layers of every client, from lowest to highest priority.
+    ListLayers,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LayerInfo {
+    pub name: String,
+    pub priority: i32,
+    pub kind: &'static str,
+    /// Was the layer created by the client that asked?
+    pub own: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layers: Option<Vec<LayerInfo>>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn error(error: impl ToString) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(request: &str) -> serde_json::Result<Request> {
+        serde_json::from_str(request)
+    }
+
+    #[test]
+    fn defaults() {
+        let request = parse(r#"{"command": "set_layer", "layer": "a"}"#).unwrap();
+        let Request::SetLayer {
+            layer,
+            priority,
+            fill,
+            keys,
+        } = request
+        else {
+            panic!("{request:?}");
+        };
+        assert_eq!((layer.as_str(), priority, fill), ("a", 0, None));
+        assert!(keys.is_empty());
+
+        let request = parse(r#"{"command": "flash", "color": "red"}"#).unwrap();
+        let Request::Flash {
+            keys,
+            color,
+            priority,
+            count,
+            interval_ms,
+        } = request
+        else {
+            panic!("{request:?}");
+        };
+        assert!(keys.is_empty());
+        assert_eq!(color, Color::Text("red".into()));
+        assert_eq!((priority, count.get(), interval_ms.get()), (100, 1, 250));
+    }
+
+    #[test]
+    fn colors() {
+        let request = r#"{"command": "set_layer", "layer": "a", "fill": [1, 2, 3],
+            "keys": {"Esc": "blue"}}"#;
+        let Request::SetLayer { fill, keys, .. } = parse(request).unwrap() else {
+            panic!();
+        };
+        assert_eq!(fill, Some(Color::Components([1, 2, 3])));
+        assert_eq!(keys["Esc"], Color::Text("blue".into()));
+    }
+
+    #[test]
+    fn invalid() {
+        for request in [
+            r#"{"command": "reboot"}"#,
+            r#"{"layer": "a"}"#,
+            // The layer is missing.
+            r#"{"command": "clear_layer"}"#,
+            r#"{"command": "flash", "color": "red", "count": 0}"#,
+            r#"{"command": "flash", "color": "red", "interval_ms": 0}"#,
+            r#"{"command": "set_layer", "layer": "a", "fill": [256, 0, 0]}"#,
+        ] {
+            assert!(parse(request).is_err(), "{request}");
+        }
+    }
+
+    #[test]
+    fn responses() {
+        let json = |response| serde_json::to_string(&response).unwrap();
+        assert_eq!(json(Response::ok()), r#"{"ok":true}"#);
+        assert_eq!(json(Response::error("No")), r#"{"ok":false,"error":"No"}"#);
+        let layers = vec![LayerInfo {
+            name: "a".into(),
+            priority: 1,
+            kind: "effect",
+            own: false,
+        }];
+        let response = Response {
+            layers: Some(layers),
+            ..Response::ok()
+        };
+        assert_eq!(
+            json(response),
+            r#"{"ok":true,"layers":[{"name":"a","priority":1,"kind":"effect","own":false}]}"#
+        );
+    }
+}
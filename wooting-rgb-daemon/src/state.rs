@@ -0,0 +1,263 @@
+//! The layers clients have created and how they are combined into the frame that is shown.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use wooting_rgb::{effect::Effect, Frame, Key};
+
+use crate::protocol::LayerInfo;
+
+/// Identifies the client connection a layer belongs to.
+pub type ClientId = u64;
+
+pub enum Content {
+    /// Static colors for some keys.
+    Keys(Vec<(Key, (u8, u8, u8))>),
+    /// An effect that is rendered every frame, covering every key.
+    Effect(Box<dyn Effect + Send>),
+    /// Keys that blink `count` times and then disappear.
+    Flash {
+        keys: Vec<Key>,
+        color: (u8, u8, u8),
+        interval: Duration,
+        count: u32,
+    },
+}
+
+impl Content {
+    fn kind(&self) -> &'static str {
+        match self {
+            Content::Keys(_) => "keys",
+            Content::Effect(_) => "effect",
+            Content::Flash { .. } => "flash",
+        }
+    }
+}
+
+struct Layer {
+    priority: i32,
+    /// Breaks ties between layers of equal priority, so the newest one wins.
+    sequence: u64,
+    started: Instant,
+    content: Content,
+}
+
+/// The layers of every client, keyed by the client that created them and their name, so
+/// clients can't replace or remove each other's layers.
+#[derive(Default)]
+pub struct State {
+    layers: HashMap<(ClientId, String), Layer>,
+    sequence: u64,
+}
+
+impl State {
+    /// Create or replace a layer. Layers with a higher priority are drawn over lower ones.
+    pub fn set_layer(&mut self, owner: ClientId, name: String, priority: i32, content: Content) {
+        self.sequence += 1;
+        let layer = Layer {
+            priority,
+            sequence: self.sequence,
+            started: Instant::now(),
+            content,
+        };
+        self.layers.insert((owner, name), layer);
+    }
+
+    /// Add a flash under a generated name that can't clash with a client's layers.
+    pub fn flash(&mut self, owner: ClientId, priority: i32, content: Content) {
+        let name = format!("flash-{}", self.sequence + 1);
+        self.set_layer(owner, name, priority, content);
+    }
+
+    /// Remove a layer the client created. Returns `false` if it has no layer with the name.
+    pub fn clear_layer(&mut self, owner: ClientId, name: &str) -> bool {
+        self.layers.remove(&(owner, name.to_owned())).is_some()
+    }
+
+    /// Remove every layer a client created, once it disconnects.
+    pub fn remove_client(&mut self, owner: ClientId) {
+        self.layers.retain(|(client, _), _| *client != owner);
+    }
+
+    /// Every layer of every client, from lowest to highest priority, marking the ones `owner`
+    /// created.
+    pub fn layers(&self, owner: ClientId) -> Vec<LayerInfo> {
+        self.ordered()
+            .into_iter()
+            .map(|((client, name), layer)| LayerInfo {
+                name: name.clone(),
+                priority: layer.priority,
+                kind: layer.content.kind(),
+                own: *client == owner,
+            })
+            .collect()
+    }
+
+    /// Combine the layers into the frame to show at `now`, dropping flashes that are done.
+    pub fn compose(&mut self, now: Instant) -> Frame {
+        self.layers.retain(|_, layer| match layer.content {
+            Content::Flash {
+                interval, count, ..
+            } => {
+                now.duration_since(layer.started) < interval.saturating_mul(2).saturating_mul(count)
+            }
+            _ => true,
+        });
+
+        let mut order: Vec<&mut Layer> = self.layers.values_mut().collect();
+        order.sort_by_key(|layer| (layer.priority, layer.sequence));
+
+        let mut frame = Frame::default();
+        let mut rendered = Frame::default();
+        for layer in order {
+            let elapsed = now.duration_since(layer.started);
+            match &mut layer.content {
+                Content::Keys(keys) => {
+                    for &(key, color) in keys.iter() {
+                        frame.set(key, color);
+                    }
+                }
+                Content::Effect(effect) => {
+                    effect.render(elapsed, &mut rendered);
+                    frame = rendered;
+                }
+                Content::Flash {
+                    keys,
+                    color,
+                    interval,
+                    ..
+                } => {
+                    let on = (elapsed.as_nanos() / interval.as_nanos().max(1)).is_multiple_of(2);
+                    if on {
+                        for &key in keys.iter() {
+                            frame.set(key, *color);
+                        }
+                    }
+                }
+            }
+        }
+        frame
+    }
+
+    fn ordered(&self) -> Vec<(&(ClientId, String), &Layer)> {
+        let mut order: Vec<_> = self.layers.iter().collect();
+        order.sort_by_key(|(_, layer)| (layer.priority, layer.sequence));
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: (u8, u8, u8) = (255, 0, 0);
+    const GREEN: (u8, u8, u8) = (0, 255, 0);
+    const BLUE: (u8, u8, u8) = (0, 0, 255);
+
+    fn keys(keys: &[(Key, (u8, u8, u8))]) -> Content {
+        Content::Keys(keys.to_vec())
+    }
+
+    #[test]
+    fn higher_priority_wins() {
+        let mut state = State::default();
+        state.set_layer(1, "high".into(), 10, keys(&[(Key::A, RED)]));
+        state.set_layer(
+            1,
+            "low".into(),
+            0,
+            keys(&[(Key::A, GREEN), (Key::B, GREEN)]),
+        );
+
+        let frame = state.compose(Instant::now());
+        assert_eq!(frame.get(Key::A), Some(RED));
+        // Lower layers show where higher ones don't color a key.
+        assert_eq!(frame.get(Key::B), Some(GREEN));
+        assert_eq!(frame.get(Key::C), Some((0, 0, 0)));
+    }
+
+    #[test]
+    fn newest_wins_ties() {
+        let mut state = State::default();
+        state.set_layer(1, "first".into(), 5, keys(&[(Key::A, RED)]));
+        state.set_layer(2, "second".into(), 5, keys(&[(Key::A, GREEN)]));
+        assert_eq!(state.compose(Instant::now()).get(Key::A), Some(GREEN));
+
+        // Replacing a layer makes it the newest.
+        state.set_layer(1, "first".into(), 5, keys(&[(Key::A, BLUE)]));
+        assert_eq!(state.compose(Instant::now()).get(Key::A), Some(BLUE));
+    }
+
+    #[test]
+    fn effects_cover_lower_layers() {
+        let mut state = State::default();
+        state.set_layer(1, "keys".into(), 0, keys(&[(Key::A, RED)]));
+        let effect = |_: Duration, frame: &mut Frame| frame.fill(BLUE);
+        state.set_layer(1, "effect".into(), 1, Content::Effect(Box::new(effect)));
+        state.set_layer(1, "top".into(), 2, keys(&[(Key::B, GREEN)]));
+
+        let frame = state.compose(Instant::now());
+        assert_eq!(frame.get(Key::A), Some(BLUE));
+        assert_eq!(frame.get(Key::B), Some(GREEN));
+    }
+
+    #[test]
+    fn flashes_blink_and_expire() {
+        let mut state = State::default();
+        let start = Instant::now();
+        state.set_layer(1, "base".into(), 0, keys(&[(Key::A, GREEN)]));
+        let flash = Content::Flash {
+            keys: vec![Key::A],
+            color: RED,
+            interval: Duration::from_millis(100),
+            count: 2,
+        };
+        state.flash(1, 100, flash);
+
+        let at = |millis| start + Duration::from_millis(millis);
+        assert_eq!(state.compose(at(50)).get(Key::A), Some(RED));
+        assert_eq!(state.compose(at(150)).get(Key::A), Some(GREEN));
+        assert_eq!(state.compose(at(250)).get(Key::A), Some(RED));
+        assert_eq!(state.layers(1).len(), 2);
+        assert_eq!(state.compose(at(450)).get(Key::A), Some(GREEN));
+        assert_eq!(state.layers(1).len(), 1);
+    }
+
+    #[test]
+    fn long_flashes_dont_overflow() {
+        let mut state = State::default();
+        let flash = Content::Flash {
+            keys: vec![Key::A],
+            color: RED,
+            interval: Duration::from_millis(u64::MAX),
+            count: u32::MAX,
+        };
+        state.flash(1, 0, flash);
+        assert_eq!(state.compose(Instant::now()).get(Key::A), Some(RED));
+    }
+
+    #[test]
+    fn layers_belong_to_their_client() {
+        let mut state = State::default();
+        state.set_layer(1, "status".into(), 0, keys(&[(Key::A, RED)]));
+        state.set_layer(2, "status".into(), 1, keys(&[(Key::B, GREEN)]));
+
+        // Both clients keep a layer of the same name.
+        let frame = state.compose(Instant::now());
+        assert_eq!(frame.get(Key::A), Some(RED));
+        assert_eq!(frame.get(Key::B), Some(GREEN));
+        let own: Vec<_> = state.layers(1).iter().map(|layer| layer.own).collect();
+        assert_eq!(own, [true, false]);
+
+        // A client can only clear its own layers.
+        assert!(!state.clear_layer(3, "status"));
+        assert!(state.clear_layer(2, "status"));
+        assert!(!state.clear_layer(2, "status"));
+        assert_eq!(state.compose(Instant::now()).get(Key::A), Some(RED));
+
+        state.remove_client(1);
+        assert!(state.layers(1).is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+use std::{env, thread::sleep, time::Duration};
+
+use wooting_rgb::{
+    image::{self, ScaleMode},
+    RgbKeyboard,
+};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    let path = env::args().nth(1).expect("Usage: array_set_image <image>");
+    let frame = image::open(&path, ScaleMode::Fill).expect("Unable to load image");
+
+    let mut keyboard = RgbKeyboard::open().unwrap();
+
+    keyboard.array_set_frame(&frame);
+    println!("Updating... {}", keyboard.array_update());
+    sleep(Duration::from_millis(5000));
+
+    println!("Finished!");
+}
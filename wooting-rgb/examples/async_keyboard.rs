@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use futures::SinkExt;
+use wooting_rgb::{AsyncRgbKeyboard, Frame, Key};
+
+#[tokio::main]
+async fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    let mut keyboard = AsyncRgbKeyboard::open().unwrap();
+    keyboard.direct_set_key(Key::Escape, 255, 0, 0).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // Fade in faster than the keyboard can keep up, which drops the frames it can't show.
+    for brightness in 0..=255 {
+        keyboard
+            .feed(Frame::filled((0, 0, brightness)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    keyboard.flush().await.unwrap();
+    println!("Dropped {} stale frames", keyboard.dropped_frames());
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}
@@ -0,0 +1,25 @@
+use std::{env, thread};
+
+use wooting_rgb::{dbus::DbusService, RgbKeyboard};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    // Publish on the bus at the given address, or on the session bus.
+    let keyboard = RgbKeyboard::open().unwrap();
+    let _service = match env::args().nth(1) {
+        Some(address) => DbusService::with_address(&address, keyboard),
+        None => DbusService::session(keyboard),
+    }
+    .unwrap();
+
+    println!(
+        "Try: busctl --user call io.wooting.Rgb /io/wooting/Rgb io.wooting.Rgb1 Fill s orange"
+    );
+    loop {
+        thread::park();
+    }
+}
@@ -99,7 +99,7 @@ fn main() {
         wooting_rgb::is_wooting_keyboard_connected()
     );
 
-    let mut keyboard = RgbKeyboard;
+    let mut keyboard = RgbKeyboard::open().unwrap();
 
     for key in ALL_KEYS {
         println!("Setting {} to white!", key);
@@ -0,0 +1,15 @@
+use wooting_rgb::{http::HttpServer, RgbKeyboard};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    let server = HttpServer::local(RgbKeyboard::open().unwrap()).unwrap();
+    println!(
+        "Try: curl -X PUT http://{}/keys/Esc -d '{{\"color\": \"red\"}}'",
+        server.local_addr()
+    );
+    server.run();
+}
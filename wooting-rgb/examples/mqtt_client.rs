@@ -0,0 +1,22 @@
+use wooting_rgb::{
+    mqtt::{MqttBridge, MqttOptions, MqttTopics},
+    RgbKeyboard,
+};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    let host = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "localhost".into());
+    let topics = MqttTopics::default();
+    println!(
+        "Try: mosquitto_pub -h {host} -t {} -m '{{\"keys\": {{\"Esc\": \"red\"}}}}'",
+        topics.set
+    );
+    let options = MqttOptions::new("wooting-rgb", host, 1883);
+    MqttBridge::new(options, topics, RgbKeyboard::open().unwrap()).run();
+}
@@ -0,0 +1,28 @@
+use std::env;
+
+use wooting_rgb::{
+    animation::{Animation, PlaybackOptions},
+    image::ScaleMode,
+    RgbKeyboard,
+};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    let path = env::args().nth(1).expect("Usage: play_animation <gif>");
+    let animation = Animation::open_gif(&path, ScaleMode::Fill).expect("Unable to load GIF");
+
+    let mut keyboard = RgbKeyboard::open().unwrap();
+
+    let options = PlaybackOptions {
+        loops: Some(3),
+        ping_pong: true,
+        ..Default::default()
+    };
+    println!("Playing... {:?}", animation.play(&mut keyboard, &options));
+
+    println!("Finished!");
+}
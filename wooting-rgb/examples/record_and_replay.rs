@@ -0,0 +1,36 @@
+use std::{thread::sleep, time::Duration};
+
+use wooting_rgb::{recording::Recorder, Key, RgbDevice, RgbKeyboard, VirtualKeyboard};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    println!("Recording...");
+    let mut recorder = Recorder::new(VirtualKeyboard::default());
+    for key in [Key::Q, Key::W, Key::E, Key::R, Key::T, Key::Y] {
+        recorder.array_set_single(key, 255, 255, 255);
+        recorder.array_update();
+        sleep(Duration::from_millis(250));
+    }
+    let (_, recording) = recorder.finish();
+
+    let path = std::env::temp_dir().join("record_and_replay.wrgb");
+    recording.save(&path).expect("Unable to save recording");
+    println!(
+        "Saved {} events to {}",
+        recording.events().len(),
+        path.display()
+    );
+
+    println!("Replaying at double speed...");
+    let mut keyboard = RgbKeyboard::open().unwrap();
+    recording
+        .replay(&mut keyboard, 2.0)
+        .expect("Unable to replay recording");
+    sleep(Duration::from_millis(1000));
+
+    println!("Finished!");
+}
@@ -9,7 +9,7 @@ fn main() {
     );
 
     println!("Resetting keyboard colors...");
-    let mut keyboard = RgbKeyboard;
+    let mut keyboard = RgbKeyboard::open().unwrap();
 
     keyboard.array_set_single(Key::Q, 255, 255, 255);
     keyboard.array_set_single(Key::W, 255, 255, 255);
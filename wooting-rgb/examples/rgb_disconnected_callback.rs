@@ -1,6 +1,9 @@
 use std::{process::exit, thread::sleep, time::Duration};
 
-use wooting_rgb::{Key, RgbKeyboard};
+use wooting_rgb::{
+    events::{self, ConnectionEvent},
+    Key, RgbKeyboard,
+};
 
 fn main() {
     println!("Waiting until keyboard is connected...");
@@ -12,18 +15,20 @@ fn main() {
     }
     println!("Connected...");
 
-    println!("Setting callback...");
-    wooting_rgb::set_disconnected_callback(|| {
-        println!("Callback triggered...");
-        println!("Finished!");
-        exit(0);
+    println!("Subscribing to events...");
+    let _subscription = events::subscribe(|event| {
+        println!("Event received: {event:?}");
+        if let ConnectionEvent::Disconnected = event {
+            println!("Finished!");
+            exit(0);
+        }
     });
 
     println!("Waiting until disconnect...");
-    let mut keyboard = RgbKeyboard;
+    let mut keyboard = RgbKeyboard::open().unwrap();
     let mut set = true;
     loop {
-        // Trigger a read so that a disconnect will be noticed. This is a limitation of that API.
+        // A failed write is noticed right away, without waiting for the next check.
         if set {
             keyboard.direct_reset_key(Key::Escape);
         } else {
@@ -0,0 +1,25 @@
+use std::{env, time::Duration};
+
+use wooting_rgb::{animation::PlaybackOptions, text::ScrollingText, RgbKeyboard};
+
+fn main() {
+    println!(
+        "Keyboard connected? {}",
+        wooting_rgb::is_wooting_keyboard_connected()
+    );
+
+    let message = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Hello Wooting!".into());
+    let text = ScrollingText::new(&message, (255, 255, 255), (0, 0, 0));
+
+    let mut keyboard = RgbKeyboard::open().unwrap();
+
+    println!(
+        "Scrolling... {:?}",
+        text.to_animation(Duration::from_millis(100))
+            .play(&mut keyboard, &PlaybackOptions::default())
+    );
+
+    println!("Finished!");
+}
@@ -0,0 +1,15 @@
+use std::time::Duration;
+
+use wooting_rgb::{animation::PlaybackOptions, preview::TerminalPreview, text::ScrollingText};
+
+fn main() {
+    let mut preview = TerminalPreview::stdout();
+    preview.set_live(true);
+
+    let text = ScrollingText::new("Preview", (0, 255, 128), (16, 16, 16));
+    text.to_animation(Duration::from_millis(80))
+        .play(&mut preview, &PlaybackOptions::default())
+        .unwrap();
+
+    println!("Finished!");
+}
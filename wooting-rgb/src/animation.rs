@@ -0,0 +1,311 @@
+//! Play sequences of frames on the keyboard, such as animated GIFs.
+//!
+//! Loading animations from files requires the `image` feature.
+
+#[cfg(feature = "image")]
+use std::{fs::File, io::BufReader, path::Path};
+use std::{iter, thread::sleep, time::Duration};
+
+#[cfg(feature = "image")]
+use ::image::{codecs::gif::GifDecoder, AnimationDecoder, ImageResult};
+
+#[cfg(feature = "image")]
+use crate::image::{self, ScaleMode};
+use crate::{Frame, RgbDevice, WootingError};
+
+/// How long a frame is shown when its file does not specify a delay. Browsers treat GIF frames
+/// without a delay the same way.
+#[cfg(feature = "image")]
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// Options that control how an [`Animation`] is played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlaybackOptions {
+    /// How many times the animation is played, or `None` to play it forever.
+    pub loops: Option<u32>,
+    /// Playback speed multiplier. `2.0` plays the animation twice as fast, `0.5` half as fast.
+    /// Must be a finite number greater than zero, and not so small that a delay grows too long
+    /// for a [`Duration`], or playback fails with [`WootingError::InvalidSpeed`].
+    pub speed: f32,
+    /// Play the animation forwards and then backwards, instead of jumping back to the first
+    /// frame after the last.
+    pub ping_pong: bool,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            loops: Some(1),
+            speed: 1.0,
+            ping_pong: false,
+        }
+    }
+}
+
+/// A sequence of frames, each shown for its own duration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Animation {
+    frames: Vec<(Frame, Duration)>,
+}
+
+impl Animation {
+    /// Create an animation from frames and the duration each frame is shown for.
+    pub fn new(frames: Vec<(Frame, Duration)>) -> Self {
+        Self { frames }
+    }
+
+    /// Return the frames of the animation along with their durations.
+    pub fn frames(&self) -> &[(Frame, Duration)] {
+        &self.frames
+    }
+
+    /// Decode an animated GIF and map every frame onto the keyboard. Transparent pixels are
+    /// shown as black and frames without a delay are shown for 100 ms.
+    #[cfg(feature = "image")]
+    pub fn open_gif<P: AsRef<Path>>(path: P, mode: ScaleMode) -> ImageResult<Self> {
+        let decoder = GifDecoder::new(BufReader::new(File::open(path)?))?;
+        let mut frames = Vec::new();
+        for frame in decoder.into_frames() {
+            let frame = frame?;
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            let delay = match Duration::from_millis(numerator as u64) / denominator.max(1) {
+                Duration::ZERO => DEFAULT_DELAY,
+                delay => delay,
+            };
+            let buffer = frame.into_buffer();
+            let pixels: Vec<u8> = buffer
+                .pixels()
+                .flat_map(|pixel| {
+                    let [red, green, blue, alpha] = pixel.0;
+                    [red, green, blue]
+                        .map(|component| (component as u16 * alpha as u16 / u8::MAX as u16) as u8)
+                })
+                .collect();
+            let frame = image::frame_from_pixels(&pixels, buffer.width(), buffer.height(), mode)
+                .expect("decoded frame buffer always matches its dimensions");
+            frames.push((frame, delay));
+        }
+        Ok(Self::new(frames))
+    }
+
+    /// Load every image in a directory as a frame, ordered by the number in each file name
+    /// (`frame1.png`, `frame2.png`, .., `frame10.png`). Every frame is shown for `delay`.
+    /// Files that are not images are skipped.
+    #[cfg(feature = "image")]
+    pub fn open_sequence<P: AsRef<Path>>(
+        directory: P,
+        delay: Duration,
+        mode: ScaleMode,
+    ) -> ImageResult<Self> {
+        let mut paths = Vec::new();
+        for entry in directory.as_ref().read_dir()? {
+            let path = entry?.path();
+            if path.is_file() && ::image::ImageFormat::from_path(&path).is_ok() {
+                paths.push(path);
+            }
+        }
+        paths.sort_by_cached_key(|path| {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let number = name
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect::<String>()
+                .parse::<u64>()
+                .ok();
+            (number, name)
+        });
+
+        let mut frames = Vec::with_capacity(paths.len());
+        for path in paths {
+            frames.push((image::open(path, mode)?, delay));
+        }
+        Ok(Self::new(frames))
+    }
+
+    /// Return the order in which frames are shown and for how long, according to the playback
+    /// options. The iterator never ends if the animation loops forever, unless it has no frames.
+    /// Fails with [`WootingError::InvalidSpeed`] if the speed is not a positive number, or so
+    /// small that a frame would be shown for longer than a [`Duration`] can hold.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use wooting_rgb::{
+    ///     animation::{Animation, PlaybackOptions},
+    ///     Frame,
+    /// };
+    ///
+    /// let second = Duration::from_secs(1);
+    /// let animation = Animation::new(vec![
+    ///     (Frame::filled((255, 0, 0)), second),
+    ///     (Frame::filled((0, 255, 0)), second),
+    ///     (Frame::filled((0, 0, 255)), second),
+    /// ]);
+    /// let options = PlaybackOptions {
+    ///     loops: Some(2),
+    ///     speed: 2.0,
+    ///     ping_pong: true,
+    /// };
+    /// // Red, green, blue, green, red, green, blue, green, each for half a second.
+    /// assert_eq!(animation.schedule(&options).unwrap().count(), 8);
+    ///
+    /// // An animation without frames has nothing to show, even when looping forever..
+    /// let forever = PlaybackOptions {
+    ///     loops: None,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(Animation::default().schedule(&forever).unwrap().count(), 0);
+    /// // ..and the speed has to be a positive number.
+    /// let backwards = PlaybackOptions {
+    ///     speed: -1.0,
+    ///     ..Default::default()
+    /// };
+    /// assert!(animation.schedule(&backwards).is_err());
+    /// ```
+    pub fn schedule<'a>(
+        &'a self,
+        options: &PlaybackOptions,
+    ) -> Result<impl Iterator<Item = (&'a Frame, Duration)> + 'a, WootingError> {
+        let speed = check_speed(options.speed)?;
+        let delays = self
+            .frames
+            .iter()
+            .map(|(_, delay)| scale_delay(*delay, speed))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = self.frames.len();
+        // Going backwards skips both ends so they aren't shown twice in a row.
+        let backwards = if options.ping_pong && count > 2 {
+            count - 2
+        } else {
+            0
+        };
+        let order = (0..count).chain((1..=backwards).rev());
+        let loops = match options.loops {
+            // Repeating nothing forever would never end.
+            _ if count == 0 => 0,
+            Some(loops) => loops as usize,
+            None => usize::MAX,
+        };
+
+        Ok(iter::repeat_n(order, loops)
+            .flatten()
+            .map(move |index| (&self.frames[index].0, delays[index])))
+    }
+
+    /// Play the animation on a device, blocking until it has finished. Fails with
+    /// [`WootingError::InvalidSpeed`] if the speed is not a positive number or too small, or with
+    /// [`WootingError::Disconnected`] if the device could not be updated, in which case
+    /// playback stops early.
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use wooting_rgb::{
+    ///     animation::{Animation, PlaybackOptions},
+    ///     Frame,
+    ///     RgbKeyboard,
+    /// };
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// // Blink the whole keyboard white..
+    /// let animation = Animation::new(vec![
+    ///     (Frame::filled((255, 255, 255)), Duration::from_millis(500)),
+    ///     (Frame::filled((0, 0, 0)), Duration::from_millis(500)),
+    /// ]);
+    /// // ..three times.
+    /// animation
+    ///     .play(&mut keyboard, &PlaybackOptions {
+    ///         loops: Some(3),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn play<D: RgbDevice + ?Sized>(
+        &self,
+        device: &mut D,
+        options: &PlaybackOptions,
+    ) -> Result<(), WootingError> {
+        self.play_while(device, options, || true)
+    }
+
+    /// Play the animation like [`play`](Self::play) for as long as `running` returns `true`.
+    /// `running` is called before every frame, so playback can be interrupted.
+    pub fn play_while<D, F>(
+        &self,
+        device: &mut D,
+        options: &PlaybackOptions,
+        mut running: F,
+    ) -> Result<(), WootingError>
+    where
+        D: RgbDevice + ?Sized,
+        F: FnMut() -> bool,
+    {
+        for (frame, delay) in self.schedule(options)? {
+            if !running() {
+                break;
+            }
+            device.array_set_frame(frame);
+            if !device.array_update() {
+                return Err(WootingError::Disconnected);
+            }
+            sleep(delay);
+        }
+        Ok(())
+    }
+}
+
+/// Check that a playback speed multiplier is a positive number.
+pub(crate) fn check_speed(speed: f32) -> Result<f32, WootingError> {
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(WootingError::InvalidSpeed)
+    }
+}
+
+/// Stretch or shorten a delay by a playback speed multiplier. Fails with
+/// [`WootingError::InvalidSpeed`] if the speed is so small that the delay no longer fits in a
+/// [`Duration`].
+pub(crate) fn scale_delay(delay: Duration, speed: f32) -> Result<Duration, WootingError> {
+    Duration::try_from_secs_f64(delay.as_secs_f64() / f64::from(speed))
+        .map_err(|_| WootingError::InvalidSpeed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VirtualKeyboard;
+
+    #[test]
+    fn tiny_speed() {
+        let animation = Animation::new(vec![(Frame::default(), Duration::from_millis(100))]);
+        let options = PlaybackOptions {
+            speed: 1e-30,
+            ..Default::default()
+        };
+        assert_eq!(
+            animation.schedule(&options).err(),
+            Some(WootingError::InvalidSpeed)
+        );
+        let mut keyboard = VirtualKeyboard::default();
+        assert_eq!(
+            animation.play(&mut keyboard, &options),
+            Err(WootingError::InvalidSpeed)
+        );
+
+        // A slow speed is fine as long as the delays still fit.
+        let options = PlaybackOptions {
+            speed: 1.0 / 1024.0,
+            ..Default::default()
+        };
+        let delays: Vec<_> = animation
+            .schedule(&options)
+            .unwrap()
+            .map(|(_, delay)| delay)
+            .collect();
+        assert_eq!(delays, [Duration::from_millis(102_400)]);
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+use futures_channel::oneshot;
+use futures_sink::Sink;
+
+use crate::{Frame, IntoMatrixRowColumn, RgbDevice, RgbKeyboard, WootingError};
+
+type Call = Box<dyn FnOnce(&mut dyn RgbDevice) + Send>;
+
+/// Work waiting for the I/O thread, in the order it was asked for.
+enum Job {
+    Call(Call),
+    Frame(Box<Frame>),
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    /// Frames that were replaced by newer ones before they were shown.
+    dropped: u64,
+    /// Did showing a frame from the sink fail since the sink was last flushed?
+    failed: bool,
+    /// Is the I/O thread showing a frame?
+    showing: bool,
+    /// Waiting for the frames of the sink to be shown.
+    flushing: Option<Waker>,
+    stopped: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+/// Drives a device from async code. The blocking calls to the device, which can take a USB
+/// round trip, run on a dedicated thread, so they never stall the executor. This works with
+/// any executor, including tokio.
+///
+/// Besides async versions of the methods of [`RgbKeyboard`], it is a [`Sink`] of frames that
+/// are set and shown. Frames are never queued up behind a slow device: a frame that hasn't been
+/// shown yet when the next one arrives is dropped, so the device always catches up with the
+/// latest frame.
+///
+/// Dropping it lets the I/O thread finish the work that was asked for and drop the device
+/// without waiting for it. Use [`close`](Self::close) to wait until the device is released,
+/// for instance before opening the keyboard again.
+///
+/// ```rust
+/// use futures::{executor::block_on, SinkExt};
+/// use wooting_rgb::{AsyncRgbKeyboard, Frame, Key, VirtualKeyboard};
+///
+/// block_on(async {
+///     let mut keyboard = AsyncRgbKeyboard::with_device(VirtualKeyboard::default());
+///     assert!(keyboard.array_set_single(Key::A, 255, 255, 255).await);
+///     assert!(keyboard.array_update().await);
+///
+///     for brightness in 0..=255 {
+///         keyboard.send(Frame::filled((brightness, 0, 0))).await.unwrap();
+///     }
+/// });
+/// ```
+pub struct AsyncRgbKeyboard {
+    shared: Arc<Shared>,
+    /// Completes once the I/O thread has dropped the device.
+    closed: Option<oneshot::Receiver<()>>,
+}
+
+impl AsyncRgbKeyboard {
+    /// Take ownership of the connected keyboard, which is reset when this is dropped. See
+    /// [`RgbKeyboard::open`].
+    pub fn open() -> Result<Self, WootingError> {
+        Ok(Self::with_device(RgbKeyboard::open()?))
+    }
+
+    /// Drive any device, such as a [`VirtualKeyboard`](crate::VirtualKeyboard). The device is
+    /// dropped on the I/O thread when this is dropped.
+    pub fn with_device<D: RgbDevice + Send + 'static>(mut device: D) -> Self {
+        let shared = Arc::new(Shared::default());
+        let (sender, closed) = oneshot::channel();
+        {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                shared.serve(&mut device);
+                drop(device);
+                let _ = sender.send(());
+            });
+        }
+        Self {
+            shared,
+            closed: Some(closed),
+        }
+    }
+
+    /// Finish the work that was asked for and wait until the device is dropped, which resets
+    /// a keyboard and lets it be opened again.
+    ///
+    /// ```rust,no_run
+    /// use futures::executor::block_on;
+    /// use wooting_rgb::AsyncRgbKeyboard;
+    ///
+    /// block_on(async {
+    ///     let keyboard = AsyncRgbKeyboard::open().unwrap();
+    ///     keyboard.close().await;
+    ///     // The keyboard was released, so it can be opened again right away.
+    ///     let keyboard = AsyncRgbKeyboard::open().unwrap();
+    /// });
+    /// ```
+    pub async fn close(mut self) {
+        self.shared.stop();
+        if let Some(closed) = self.closed.take() {
+            let _ = closed.await;
+        }
+    }
+
+    /// Run `call` with the device on the I/O thread. Returns `None` if the thread is gone.
+    async fn call<R, F>(&self, call: F) -> Option<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut dyn RgbDevice) -> R + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.shared.push(Job::Call(Box::new(move |device| {
+            let _ = sender.send(call(device));
+        })));
+        receiver.await.ok()
+    }
+
+    /// Set the color of a single key without influencing the color array. See
+    /// [`RgbKeyboard::direct_set_key`].
+    pub async fn direct_set_key<K: IntoMatrixRowColumn>(
+        &self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        let row_and_column = key.get_matrix_row_and_column();
+        self.call(move |device| device.direct_set_key_matrix(row_and_column, red, green, blue))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Reset the color of a single key. See [`RgbKeyboard::direct_reset_key`].
+    pub async fn direct_reset_key<K: IntoMatrixRowColumn>(&self, key: K) -> bool {
+        let row_and_column = key.get_matrix_row_and_column();
+        self.call(move |device| device.direct_reset_key_matrix(row_and_column))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Set a single color in the color array. See [`RgbKeyboard::array_set_single`].
+    pub async fn array_set_single<K: IntoMatrixRowColumn>(
+        &self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        let row_and_column = key.get_matrix_row_and_column();
+        self.call(move |device| device.array_set_single_matrix(row_and_column, red, green, blue))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Set a complete color array. See [`RgbKeyboard::array_set_frame`].
+    pub async fn array_set_frame(&self, frame: Frame) -> bool {
+        self.call(move |device| device.array_set_frame(&frame))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Apply any updates made to the color array. See [`RgbKeyboard::array_update`].
+    pub async fn array_update(&self) -> bool {
+        self.call(|device| device.array_update())
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Set an auto-update trigger after every change to the color array. See
+    /// [`RgbKeyboard::array_auto_update`].
+    pub async fn array_auto_update(&self, auto_update: bool) {
+        self.call(move |device| device.array_auto_update(auto_update))
+            .await;
+    }
+
+    /// Restore all colors to those that were originally on the device. See
+    /// [`RgbKeyboard::reset_all`].
+    pub async fn reset_all(&self) -> bool {
+        self.call(|device| device.reset_all())
+            .await
+            .unwrap_or(false)
+    }
+
+    /// How many frames sent to the sink were dropped because a newer one arrived before they
+    /// were shown.
+    pub fn dropped_frames(&self) -> u64 {
+        self.shared.queue.lock().unwrap().dropped
+    }
+}
+
+impl Queue {
+    /// Is a frame from the sink waiting or being shown?
+    fn frame_pending(&self) -> bool {
+        self.showing || self.jobs.iter().any(|job| matches!(job, Job::Frame(_)))
+    }
+}
+
+impl Shared {
+    /// Let the I/O thread stop once it has done the queued work.
+    fn stop(&self) {
+        self.queue.lock().unwrap().stopped = true;
+        self.ready.notify_one();
+    }
+
+    fn push(&self, job: Job) {
+        let mut queue = self.queue.lock().unwrap();
+        if let (Job::Frame(_), Some(Job::Frame(_))) = (&job, queue.jobs.back()) {
+            queue.jobs.pop_back();
+            queue.dropped += 1;
+        }
+        queue.jobs.push_back(job);
+        self.ready.notify_one();
+    }
+
+    /// Do the queued work until the handle is dropped.
+    fn serve(&self, device: &mut dyn RgbDevice) {
+        loop {
+            let job = {
+                let mut queue = self.queue.lock().unwrap();
+                loop {
+                    if let Some(job) = queue.jobs.pop_front() {
+                        queue.showing = matches!(job, Job::Frame(_));
+                        break job;
+                    }
+                    if queue.stopped {
+                        return;
+                    }
+                    queue = self.ready.wait(queue).unwrap();
+                }
+            };
+            match job {
+                Job::Call(call) => call(device),
+                Job::Frame(frame) => {
+                    let shown = device.array_set_frame(&frame) && device.array_update();
+                    let mut queue = self.queue.lock().unwrap();
+                    queue.showing = false;
+                    queue.failed |= !shown;
+                    if !queue.frame_pending() {
+                        if let Some(waker) = queue.flushing.take() {
+                            waker.wake();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Sink<Frame> for AsyncRgbKeyboard {
+    type Error = WootingError;
+
+    /// Always ready, since a newer frame replaces one that is still waiting.
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: Frame) -> Result<(), Self::Error> {
+        self.shared.push(Job::Frame(Box::new(frame)));
+        Ok(())
+    }
+
+    /// Wait for the last frame to be shown, failing if showing any frame since the last flush
+    /// failed.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.frame_pending() {
+            queue.flushing = Some(cx.waker().clone());
+            Poll::Pending
+        } else if std::mem::take(&mut queue.failed) {
+            Poll::Ready(Err(WootingError::Disconnected))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl Drop for AsyncRgbKeyboard {
+    /// Stop the I/O thread without waiting for it, so dropping never blocks the executor.
+    fn drop(&mut self) {
+        self.shared.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use futures::{executor::block_on, SinkExt};
+
+    use super::*;
+    use crate::VirtualKeyboard;
+
+    /// What a [`Slow`] device did, and how it behaves.
+    #[derive(Default)]
+    struct Probe {
+        /// The frames that were shown by `array_update`.
+        shown: Mutex<Vec<Frame>>,
+        /// Makes `array_update` fail.
+        failing: AtomicBool,
+        dropped: AtomicBool,
+    }
+
+    /// A device that holds up every update for a while and tells what it does.
+    struct Slow(VirtualKeyboard, Arc<Probe>);
+
+    impl Drop for Slow {
+        fn drop(&mut self) {
+            self.1.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl RgbDevice for Slow {
+        fn direct_set_key_matrix(
+            &mut self,
+            position: (u8, u8),
+            red: u8,
+            green: u8,
+            blue: u8,
+        ) -> bool {
+            self.0.direct_set_key_matrix(position, red, green, blue)
+        }
+
+        fn direct_reset_key_matrix(&mut self, position: (u8, u8)) -> bool {
+            self.0.direct_reset_key_matrix(position)
+        }
+
+        fn array_set_single_matrix(
+            &mut self,
+            position: (u8, u8),
+            red: u8,
+            green: u8,
+            blue: u8,
+        ) -> bool {
+            self.0.array_set_single_matrix(position, red, green, blue)
+        }
+
+        fn array_set_frame(&mut self, frame: &Frame) -> bool {
+            self.0.array_set_frame(frame)
+        }
+
+        fn array_update(&mut self) -> bool {
+            thread::sleep(Duration::from_millis(200));
+            if self.1.failing.load(Ordering::SeqCst) {
+                return false;
+            }
+            let updated = self.0.array_update();
+            self.1.shown.lock().unwrap().push(self.0.frame());
+            updated
+        }
+
+        fn array_auto_update(&mut self, auto_update: bool) {
+            self.0.array_auto_update(auto_update)
+        }
+
+        fn reset_all(&mut self) -> bool {
+            self.0.reset_all()
+        }
+    }
+
+    fn slow() -> (AsyncRgbKeyboard, Arc<Probe>) {
+        let probe = Arc::new(Probe::default());
+        let device = Slow(VirtualKeyboard::default(), probe.clone());
+        (AsyncRgbKeyboard::with_device(device), probe)
+    }
+
+    #[test]
+    fn drop_doesnt_wait() {
+        let (keyboard, probe) = slow();
+        keyboard.shared.push(Job::Frame(Box::default()));
+        let start = Instant::now();
+        drop(keyboard);
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        // The queued frame is still shown before the device is dropped.
+        while !probe.dropped.load(Ordering::SeqCst) {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn close_waits() {
+        let (keyboard, probe) = slow();
+        keyboard.shared.push(Job::Frame(Box::default()));
+        block_on(keyboard.close());
+        assert!(probe.dropped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn sink() {
+        let (mut keyboard, probe) = slow();
+        let frames: Vec<_> = (0..5).map(|red| Frame::filled((red, 0, 0))).collect();
+        block_on(async {
+            // Wait for the device to be busy showing the first frame.
+            keyboard.feed(frames[0]).await.unwrap();
+            let start = Instant::now();
+            while !keyboard.shared.queue.lock().unwrap().showing {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            // Only the last of the frames that arrive meanwhile is shown.
+            for frame in &frames[1..] {
+                keyboard.feed(*frame).await.unwrap();
+            }
+            keyboard.flush().await.unwrap();
+            assert_eq!(*probe.shown.lock().unwrap(), [frames[0], frames[4]]);
+            assert_eq!(keyboard.dropped_frames(), 3);
+
+            // A frame that can't be shown fails the next flush, and only that one.
+            probe.failing.store(true, Ordering::SeqCst);
+            keyboard.feed(frames[1]).await.unwrap();
+            assert_eq!(keyboard.flush().await, Err(WootingError::Disconnected));
+            assert_eq!(keyboard.flush().await, Ok(()));
+            assert_eq!(probe.shown.lock().unwrap().len(), 2);
+        });
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+use crate::WootingError;
+
+/// A color as it is given in a request, either as a string understood by [`parse_color`] or as
+/// its red, green and blue components. With the `serde` feature it deserializes from a string
+/// or a `[red, green, blue]` array.
+///
+/// ```rust
+/// use wooting_rgb::Color;
+///
+/// assert_eq!(Color::Text("orange".into()).parse(), Ok((255, 128, 0)));
+/// assert_eq!(Color::Components([255, 128, 0]).parse(), Ok((255, 128, 0)));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(untagged))]
+pub enum Color {
+    Text(String),
+    Components([u8; 3]),
+}
+
+impl Color {
+    /// Return the red, green and blue components of the color, or
+    /// [`WootingError::InvalidColor`] if the text isn't a color.
+    pub fn parse(&self) -> Result<(u8, u8, u8), WootingError> {
+        match self {
+            Color::Text(text) => parse_color(text),
+            Color::Components([red, green, blue]) => Ok((*red, *green, *blue)),
+        }
+    }
+}
+
+/// Parse a color from a string. Accepted are hex colors (`#ff8000`, `ff8000` or the short
+/// form `#f80`), comma separated components (`255,128,0`) and a handful of names (`black`,
+/// `white`, `red`, `green`, `blue`, `yellow`, `cyan`, `magenta`, `orange`, `purple`, `pink`).
+/// Returns [`WootingError::InvalidColor`] for anything else.
+///
+/// ```rust
+/// use wooting_rgb::parse_color;
+///
+/// assert_eq!(parse_color("#ff8000"), Ok((255, 128, 0)));
+/// assert_eq!(parse_color("f80"), Ok((255, 136, 0)));
+/// assert_eq!(parse_color("255, 128, 0"), Ok((255, 128, 0)));
+/// assert_eq!(parse_color("Orange"), Ok((255, 128, 0)));
+/// ```
+pub fn parse_color(color: &str) -> Result<(u8, u8, u8), WootingError> {
+    let color = color.trim();
+    let named = match color.to_ascii_lowercase().as_str() {
+        "black" | "off" => Some((0, 0, 0)),
+        "white" => Some((255, 255, 255)),
+        "red" => Some((255, 0, 0)),
+        "green" => Some((0, 255, 0)),
+        "blue" => Some((0, 0, 255)),
+        "yellow" => Some((255, 255, 0)),
+        "cyan" => Some((0, 255, 255)),
+        "magenta" => Some((255, 0, 255)),
+        "orange" => Some((255, 128, 0)),
+        "purple" => Some((128, 0, 255)),
+        "pink" => Some((255, 105, 180)),
+        _ => None,
+    };
+    if let Some(named) = named {
+        return Ok(named);
+    }
+
+    if color.contains(',') {
+        let components: Vec<u8> = color
+            .split(',')
+            .map(|component| component.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| WootingError::InvalidColor)?;
+        return match components[..] {
+            [red, green, blue] => Ok((red, green, blue)),
+            _ => Err(WootingError::InvalidColor),
+        };
+    }
+
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(WootingError::InvalidColor);
+    }
+    let component = |digits: &str| u8::from_str_radix(digits, 16).unwrap_or_default();
+    match hex.len() {
+        3 => {
+            let [red, green, blue] = [0, 1, 2].map(|index| component(&hex[index..=index]) * 17);
+            Ok((red, green, blue))
+        }
+        6 => Ok((
+            component(&hex[0..2]),
+            component(&hex[2..4]),
+            component(&hex[4..6]),
+        )),
+        _ => Err(WootingError::InvalidColor),
+    }
+}
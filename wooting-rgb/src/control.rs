@@ -0,0 +1,182 @@
+//! The operations the remote control interfaces offer, shared between them: a device that
+//! colors are set on, a record of what it shows and effects running on a thread of their own.
+//! The interfaces that take JSON also share the format of their requests.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, sleep, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(any(feature = "http", feature = "mqtt"))]
+use std::collections::BTreeMap;
+
+#[cfg(any(feature = "http", feature = "mqtt"))]
+use serde::Deserialize;
+
+#[cfg(any(feature = "http", feature = "mqtt"))]
+use crate::{effect, Color, WootingError};
+use crate::{effect::Effect, Frame, Key, RgbDevice};
+
+const EFFECT_INTERVAL: Duration = Duration::from_millis(33);
+
+struct Shown {
+    device: Box<dyn RgbDevice + Send>,
+    /// The colors last sent to the device.
+    frame: Frame,
+}
+
+struct RunningEffect {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+/// Owns a device on behalf of a remote control interface. Setting colors stops any running
+/// effect, so the last request always wins.
+pub(crate) struct Controller {
+    shown: Arc<Mutex<Shown>>,
+    effect: Option<RunningEffect>,
+}
+
+impl Controller {
+    pub(crate) fn new<D: RgbDevice + Send + 'static>(device: D) -> Self {
+        Self {
+            shown: Arc::new(Mutex::new(Shown {
+                device: Box::new(device),
+                frame: Frame::default(),
+            })),
+            effect: None,
+        }
+    }
+
+    /// The colors last sent to the device, including those of a running effect.
+    pub(crate) fn frame(&self) -> Frame {
+        self.shown.lock().unwrap().frame
+    }
+
+    /// Set one key to a color. Returns `false` if the device could not be updated.
+    #[cfg(any(feature = "dbus", feature = "http"))]
+    pub(crate) fn set_key(&mut self, key: Key, color: (u8, u8, u8)) -> bool {
+        self.stop_effect();
+        let mut frame = self.frame();
+        frame.set(key, color);
+        self.shown.lock().unwrap().show(&frame)
+    }
+
+    /// Set every key. Returns `false` if the device could not be updated.
+    pub(crate) fn set_frame(&mut self, frame: &Frame) -> bool {
+        self.stop_effect();
+        self.shown.lock().unwrap().show(frame)
+    }
+
+    /// Run an effect until the colors are changed again.
+    pub(crate) fn start_effect(&mut self, mut effect: Box<dyn Effect + Send>) {
+        self.stop_effect();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let shown = self.shown.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let start = Instant::now();
+                let mut frame = Frame::default();
+                while !stop.load(Ordering::Relaxed) {
+                    effect.render(start.elapsed(), &mut frame);
+                    shown.lock().unwrap().show(&frame);
+                    sleep(EFFECT_INTERVAL);
+                }
+            })
+        };
+        self.effect = Some(RunningEffect { stop, thread });
+    }
+
+    /// Is an effect running?
+    #[cfg(any(feature = "dbus", feature = "http"))]
+    pub(crate) fn effect_running(&self) -> bool {
+        self.effect.is_some()
+    }
+
+    /// Stop the running effect, leaving its last frame on the device.
+    pub(crate) fn stop_effect(&mut self) {
+        if let Some(effect) = self.effect.take() {
+            effect.stop.store(true, Ordering::Relaxed);
+            let _ = effect.thread.join();
+        }
+    }
+
+    /// Restore the colors that were originally on the device. Returns `false` if the device
+    /// could not be reset.
+    pub(crate) fn reset(&mut self) -> bool {
+        self.stop_effect();
+        let mut shown = self.shown.lock().unwrap();
+        shown.frame = Frame::default();
+        shown.device.reset_all()
+    }
+}
+
+impl Shown {
+    fn show(&mut self, frame: &Frame) -> bool {
+        self.frame = *frame;
+        self.device.array_set_frame(frame) && self.device.array_update()
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        self.stop_effect();
+    }
+}
+
+/// Colors for many keys at once: `fill` colors every key, after which the colors in `keys` are
+/// applied, keyed by key name.
+#[cfg(any(feature = "http", feature = "mqtt"))]
+#[derive(Deserialize)]
+pub(crate) struct SetFrame {
+    #[serde(default)]
+    fill: Option<Color>,
+    #[serde(default)]
+    keys: BTreeMap<String, Color>,
+}
+
+#[cfg(any(feature = "http", feature = "mqtt"))]
+impl SetFrame {
+    /// Apply the colors on top of `frame`.
+    pub(crate) fn apply(&self, mut frame: Frame) -> Result<Frame, WootingError> {
+        if let Some(color) = &self.fill {
+            frame.fill(color.parse()?);
+        }
+        for (key, color) in &self.keys {
+            frame.set(key.parse::<Key>()?, color.parse()?);
+        }
+        Ok(frame)
+    }
+}
+
+/// One of the built-in effects, by name, with an optional color and the number of seconds one
+/// cycle takes.
+#[cfg(any(feature = "http", feature = "mqtt"))]
+#[derive(Deserialize)]
+pub(crate) struct StartEffect {
+    name: String,
+    #[serde(default)]
+    color: Option<Color>,
+    #[serde(default)]
+    period: Option<f32>,
+}
+
+#[cfg(any(feature = "http", feature = "mqtt"))]
+impl StartEffect {
+    pub(crate) fn effect(&self) -> Result<Box<dyn Effect + Send>, WootingError> {
+        let color = match &self.color {
+            Some(color) => color.parse()?,
+            None => (255, 255, 255),
+        };
+        let period = self
+            .period
+            .map(|period| effect::period_from_secs(period.into()))
+            .transpose()?;
+        effect::from_name(&self.name, color, period)
+    }
+}
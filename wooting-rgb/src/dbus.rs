@@ -0,0 +1,293 @@
+//! Expose a keyboard on D-Bus, so its lighting can be controlled from shell scripts and desktop
+//! environment hooks. Enabled by the `dbus` feature.
+//!
+//! The service is published as [`BUS_NAME`] with an object at [`OBJECT_PATH`] implementing
+//! [`INTERFACE`]:
+//!
+//! - `SetKey(s key, s color)` sets one key, by name, to a color understood by
+//!   [`parse_color`].
+//! - `Fill(s color)` sets every key to a color.
+//! - `StartEffect(s name, s color, d period)` runs one of the [built-in effects](crate::effect)
+//!   until another method changes the colors. A `period` of `0` keeps the default.
+//! - `StopEffect()` stops the running effect, leaving its last frame on the keyboard.
+//! - `Reset()` restores the colors that were originally on the keyboard.
+//! - The `Connected`, `Model`, `DeviceType` and `Layout` properties describe the keyboard, and
+//!   `EffectRunning` tells whether an effect is running.
+//! - The `Disconnected` signal is emitted when the keyboard is unplugged.
+//!
+//! ```sh
+//! busctl --user call io.wooting.Rgb /io/wooting/Rgb io.wooting.Rgb1 SetKey ss Esc red
+//! ```
+
+use std::collections::HashMap;
+
+use zbus::{blocking::Connection, fdo, interface, zvariant::Value};
+
+use crate::{
+    control::Controller,
+    device_info, effect,
+    events::{self, ConnectionEvent, Subscription},
+    is_wooting_keyboard_connected, parse_color, Frame, Key, RgbDevice,
+};
+
+/// The well-known name the service is published as.
+pub const BUS_NAME: &str = "io.wooting.Rgb";
+/// The path of the object that controls the keyboard.
+pub const OBJECT_PATH: &str = "/io/wooting/Rgb";
+/// The name of the interface the object implements.
+pub const INTERFACE: &str = "io.wooting.Rgb1";
+
+/// Publishes a device on D-Bus until it is dropped, at which point the device is dropped too.
+///
+/// ```rust,no_run
+/// use wooting_rgb::{dbus::DbusService, RgbKeyboard};
+///
+/// let service = DbusService::session(RgbKeyboard::open().unwrap()).unwrap();
+/// // Handle method calls until the process is stopped.
+/// loop {
+///     std::thread::park();
+/// }
+/// ```
+pub struct DbusService {
+    connection: Connection,
+    /// Signals the changes in which keyboard is connected.
+    _events: Subscription,
+}
+
+impl DbusService {
+    /// Publish a device on the session bus.
+    pub fn session<D: RgbDevice + Send + 'static>(device: D) -> zbus::Result<Self> {
+        Self::publish(zbus::blocking::connection::Builder::session()?, device)
+    }
+
+    /// Publish a device on the bus at `address`, such as a private `dbus-daemon` started for
+    /// testing (`unix:path=/tmp/test-bus`).
+    pub fn with_address<D: RgbDevice + Send + 'static>(
+        address: &str,
+        device: D,
+    ) -> zbus::Result<Self> {
+        Self::publish(
+            zbus::blocking::connection::Builder::address(address)?,
+            device,
+        )
+    }
+
+    fn publish<D: RgbDevice + Send + 'static>(
+        builder: zbus::blocking::connection::Builder<'_>,
+        device: D,
+    ) -> zbus::Result<Self> {
+        let keyboard = Keyboard(Controller::new(device));
+        let connection = builder
+            .serve_at(OBJECT_PATH, keyboard)?
+            .name(BUS_NAME)?
+            .build()?;
+
+        let events = {
+            let connection = connection.clone();
+            events::subscribe(move |event| signal(&connection, event))
+        };
+
+        Ok(Self {
+            connection,
+            _events: events,
+        })
+    }
+
+    /// The connection the service is published on.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Drop for DbusService {
+    fn drop(&mut self) {
+        // Stops a running effect and releases the device.
+        let _ = self
+            .connection
+            .object_server()
+            .remove::<Keyboard, _>(OBJECT_PATH);
+    }
+}
+
+/// Signal that the keyboard was unplugged, plugged in or swapped for another one.
+fn signal(connection: &Connection, event: &ConnectionEvent) {
+    if let ConnectionEvent::Disconnected = event {
+        let _ = connection.emit_signal(None::<()>, OBJECT_PATH, INTERFACE, "Disconnected", &());
+    }
+    let changed: HashMap<&str, Value> = HashMap::new();
+    let invalidated = ["Connected", "Model", "DeviceType", "Layout"];
+    let _ = connection.emit_signal(
+        None::<()>,
+        OBJECT_PATH,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        &(INTERFACE, changed, &invalidated[..]),
+    );
+}
+
+struct Keyboard(Controller);
+
+fn updated(updated: bool) -> fdo::Result<()> {
+    if updated {
+        Ok(())
+    } else {
+        Err(fdo::Error::Failed("Unable to update keyboard".into()))
+    }
+}
+
+fn invalid_args(error: impl ToString) -> fdo::Error {
+    fdo::Error::InvalidArgs(error.to_string())
+}
+
+#[interface(name = "io.wooting.Rgb1")]
+impl Keyboard {
+    /// Set one key, by name, to a color.
+    fn set_key(&mut self, key: &str, color: &str) -> fdo::Result<()> {
+        let key: Key = key.parse().map_err(invalid_args)?;
+        let color = parse_color(color).map_err(invalid_args)?;
+        updated(self.0.set_key(key, color))
+    }
+
+    /// Set every key to a color.
+    fn fill(&mut self, color: &str) -> fdo::Result<()> {
+        let frame = Frame::filled(parse_color(color).map_err(invalid_args)?);
+        updated(self.0.set_frame(&frame))
+    }
+
+    /// Run one of the built-in effects until the colors are changed again.
+    fn start_effect(&mut self, name: &str, color: &str, period: f64) -> fdo::Result<()> {
+        let color = parse_color(color).map_err(invalid_args)?;
+        let period = (period != 0.0)
+            .then(|| effect::period_from_secs(period))
+            .transpose()
+            .map_err(invalid_args)?;
+        let effect = effect::from_name(name, color, period).map_err(invalid_args)?;
+        self.0.start_effect(effect);
+        Ok(())
+    }
+
+    /// Stop the running effect, leaving its last frame on the keyboard.
+    fn stop_effect(&mut self) {
+        self.0.stop_effect();
+    }
+
+    /// Restore the colors that were originally on the keyboard.
+    fn reset(&mut self) -> fdo::Result<()> {
+        updated(self.0.reset())
+    }
+
+    /// Emitted when the keyboard is unplugged.
+    #[zbus(signal)]
+    async fn disconnected(emitter: &zbus::object_server::SignalEmitter<'_>) -> zbus::Result<()>;
+
+    /// Whether an effect is running.
+    #[zbus(property)]
+    fn effect_running(&self) -> bool {
+        self.0.effect_running()
+    }
+
+    /// Whether a keyboard is connected.
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        is_wooting_keyboard_connected()
+    }
+
+    /// The name of the model, or an empty string if there is no keyboard connected.
+    #[zbus(property)]
+    fn model(&self) -> String {
+        device_info().map(|info| info.model).unwrap_or_default()
+    }
+
+    /// The kind of device, such as `Keyboard80`.
+    #[zbus(property)]
+    fn device_type(&self) -> String {
+        device_info()
+            .map(|info| format!("{:?}", info.device_type))
+            .unwrap_or_default()
+    }
+
+    /// The physical layout of the keys, such as `Ansi`.
+    #[zbus(property)]
+    fn layout(&self) -> String {
+        device_info()
+            .map(|info| format!("{:?}", info.layout))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use zbus::blocking::Proxy;
+
+    use super::*;
+    use crate::VirtualKeyboard;
+
+    /// A private session bus that is stopped when dropped.
+    struct Bus(Child);
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// Start a private session bus, returning it and its address.
+    fn bus() -> (Bus, String) {
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--print-address", "--nofork"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Unable to start dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        (Bus(child), address.trim().to_owned())
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon"]
+    fn service() {
+        let (_bus, address) = bus();
+        let device = Arc::new(Mutex::new(VirtualKeyboard::default()));
+        let service = DbusService::with_address(&address, device.clone()).unwrap();
+        let client = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+        let proxy = Proxy::new(&client, BUS_NAME, OBJECT_PATH, INTERFACE).unwrap();
+
+        let () = proxy.call("Fill", &("blue",)).unwrap();
+        let () = proxy.call("SetKey", &("Esc", "red")).unwrap();
+        let frame = device.lock().unwrap().frame();
+        assert_eq!(frame.get(Key::Escape), Some((255, 0, 0)));
+        assert_eq!(frame.get(Key::A), Some((0, 0, 255)));
+
+        let error = proxy.call::<_, _, ()>("SetKey", &("Nope", "red"));
+        assert!(matches!(error, Err(zbus::Error::MethodError(..))));
+        let error = proxy.call::<_, _, ()>("Fill", &("chartreuse",));
+        assert!(matches!(error, Err(zbus::Error::MethodError(..))));
+        for period in [-1.0, f64::NAN, f64::INFINITY] {
+            let error = proxy.call::<_, _, ()>("StartEffect", &("rainbow", "white", period));
+            assert!(matches!(error, Err(zbus::Error::MethodError(..))));
+        }
+        assert!(!proxy.get_property::<bool>("EffectRunning").unwrap());
+
+        let connected: bool = proxy.get_property("Connected").unwrap();
+        assert_eq!(connected, is_wooting_keyboard_connected());
+
+        // Nothing is left to wait for when the service goes away.
+        let start = Instant::now();
+        drop(service);
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
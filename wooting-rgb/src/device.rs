@@ -0,0 +1,307 @@
+use crate::{Frame, IntoMatrixRowColumn, RgbKeyboard, COLUMNS, ROWS};
+
+/// Types that implement this trait can receive the same RGB operations as the keyboard. This
+/// allows code to drive a [`RgbKeyboard`], a [`VirtualKeyboard`] or any other output the same
+/// way.
+///
+/// The required methods work on matrix positions so that the trait can be used as a trait
+/// object. The provided methods accept anything that implements [`IntoMatrixRowColumn`].
+pub trait RgbDevice {
+    /// Set the color of a single key at a matrix position without influencing the color
+    /// array. See [`RgbKeyboard::direct_set_key`].
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool;
+
+    /// Reset the color of a single key at a matrix position. See
+    /// [`RgbKeyboard::direct_reset_key`].
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool;
+
+    /// Set a single color in the color array. See [`RgbKeyboard::array_set_single_matrix`].
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool;
+
+    /// Set a complete color array. See [`RgbKeyboard::array_set_frame`].
+    fn array_set_frame(&mut self, frame: &Frame) -> bool;
+
+    /// Apply any updates made to the color array. See [`RgbKeyboard::array_update`].
+    fn array_update(&mut self) -> bool;
+
+    /// Set an auto-update trigger after every change to the color array. See
+    /// [`RgbKeyboard::array_auto_update`].
+    fn array_auto_update(&mut self, auto_update: bool);
+
+    /// Restore all colors to those that were originally on the device. See
+    /// [`RgbKeyboard::reset_all`].
+    fn reset_all(&mut self) -> bool;
+
+    /// Set the color of a single key. See [`RgbKeyboard::direct_set_key`].
+    fn direct_set_key<K: IntoMatrixRowColumn>(
+        &mut self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool
+    where
+        Self: Sized,
+    {
+        self.direct_set_key_matrix(key.get_matrix_row_and_column(), red, green, blue)
+    }
+
+    /// Reset the color of a single key. See [`RgbKeyboard::direct_reset_key`].
+    fn direct_reset_key<K: IntoMatrixRowColumn>(&mut self, key: K) -> bool
+    where
+        Self: Sized,
+    {
+        self.direct_reset_key_matrix(key.get_matrix_row_and_column())
+    }
+
+    /// Set a single color in the color array. See [`RgbKeyboard::array_set_single`].
+    fn array_set_single<K: IntoMatrixRowColumn>(
+        &mut self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool
+    where
+        Self: Sized,
+    {
+        self.array_set_single_matrix(key.get_matrix_row_and_column(), red, green, blue)
+    }
+
+    /// Set a complete color array. See [`RgbKeyboard::array_set_full`].
+    fn array_set_full<K: IntoMatrixRowColumn>(&mut self, array: &[(K, (u8, u8, u8))]) -> bool
+    where
+        Self: Sized,
+    {
+        let mut frame = Frame::default();
+        for (key, color) in array {
+            frame.set(key.get_matrix_row_and_column(), *color);
+        }
+        self.array_set_frame(&frame)
+    }
+}
+
+impl RgbDevice for RgbKeyboard {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        RgbKeyboard::direct_set_key(self, row_and_column, red, green, blue)
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        RgbKeyboard::direct_reset_key(self, row_and_column)
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        RgbKeyboard::array_set_single_matrix(self, row_and_column, red, green, blue)
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        RgbKeyboard::array_set_frame(self, frame)
+    }
+
+    fn array_update(&mut self) -> bool {
+        RgbKeyboard::array_update(self)
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        RgbKeyboard::array_auto_update(self, auto_update)
+    }
+
+    fn reset_all(&mut self) -> bool {
+        RgbKeyboard::reset_all(self)
+    }
+}
+
+/// A keyboard that only exists in memory. It keeps track of the colors the same way the
+/// keyboard does, which makes it useful for previews and for running code without hardware.
+///
+/// ```rust
+/// use wooting_rgb::{Key, RgbDevice, VirtualKeyboard};
+///
+/// let mut keyboard = VirtualKeyboard::default();
+/// // Modify the keyboard array so A will be set to white..
+/// keyboard.array_set_single(Key::A, 255, 255, 255);
+/// assert_eq!(keyboard.frame().get(Key::A), Some((0, 0, 0)));
+/// // ..and apply the change.
+/// keyboard.array_update();
+/// assert_eq!(keyboard.frame().get(Key::A), Some((255, 255, 255)));
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct VirtualKeyboard {
+    /// The color array, including changes that haven't been applied yet.
+    array: Frame,
+    /// The color array as it was last applied.
+    applied: Frame,
+    /// Colors set directly on single keys, which take precedence over the color array.
+    direct: [[Option<(u8, u8, u8)>; COLUMNS]; ROWS],
+    auto_update: bool,
+}
+
+impl VirtualKeyboard {
+    /// Return the colors that are currently shown, that is the applied color array with any
+    /// directly set keys on top.
+    pub fn frame(&self) -> Frame {
+        let mut frame = self.applied;
+        for (row, columns) in self.direct.iter().enumerate() {
+            for (column, color) in columns.iter().enumerate() {
+                if let Some(color) = color {
+                    frame.set((row as u8, column as u8), *color);
+                }
+            }
+        }
+        frame
+    }
+
+    /// Is auto-update of the color array enabled?
+    pub fn auto_update(&self) -> bool {
+        self.auto_update
+    }
+
+    fn direct_slot(&mut self, (row, column): (u8, u8)) -> Option<&mut Option<(u8, u8, u8)>> {
+        self.direct
+            .get_mut(row as usize)
+            .and_then(|row| row.get_mut(column as usize))
+    }
+}
+
+impl RgbDevice for VirtualKeyboard {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        match self.direct_slot(row_and_column) {
+            Some(slot) => {
+                *slot = Some((red, green, blue));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        match self.direct_slot(row_and_column) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        if !self.array.set(row_and_column, (red, green, blue)) {
+            return false;
+        }
+        if self.auto_update {
+            self.array_update();
+        }
+        true
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.array = *frame;
+        if self.auto_update {
+            self.array_update();
+        }
+        true
+    }
+
+    fn array_update(&mut self) -> bool {
+        // Sending the color array overrides any directly set keys.
+        self.applied = self.array;
+        self.direct = Default::default();
+        true
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        self.auto_update = auto_update;
+    }
+
+    fn reset_all(&mut self) -> bool {
+        *self = Self {
+            auto_update: self.auto_update,
+            ..Default::default()
+        };
+        true
+    }
+}
+
+/// Lets tests keep a handle on a device they hand over to a service.
+#[cfg(test)]
+impl<D: RgbDevice> RgbDevice for std::sync::Arc<std::sync::Mutex<D>> {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.lock()
+            .unwrap()
+            .direct_set_key_matrix(row_and_column, red, green, blue)
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        self.lock().unwrap().direct_reset_key_matrix(row_and_column)
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.lock()
+            .unwrap()
+            .array_set_single_matrix(row_and_column, red, green, blue)
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.lock().unwrap().array_set_frame(frame)
+    }
+
+    fn array_update(&mut self) -> bool {
+        self.lock().unwrap().array_update()
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        self.lock().unwrap().array_auto_update(auto_update)
+    }
+
+    fn reset_all(&mut self) -> bool {
+        self.lock().unwrap().reset_all()
+    }
+}
@@ -0,0 +1,244 @@
+//! Lighting effects that are rendered frame by frame, along with a few built-in effects.
+
+use std::{
+    f32::consts::TAU,
+    time::{Duration, Instant},
+};
+
+use crate::{pacing::FramePacer, Frame, Key, RgbDevice, WootingError, LAYOUT_WIDTH};
+
+/// The names of the built-in effects, as accepted by [`from_name`].
+pub const NAMES: [&str; 3] = ["breathing", "rainbow", "spectrum"];
+
+/// Types that implement this trait can render an animated lighting effect.
+pub trait Effect {
+    /// Render the effect as it looks `elapsed` after it started.
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame);
+}
+
+impl<F: FnMut(Duration, &mut Frame)> Effect for F {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        self(elapsed, frame)
+    }
+}
+
+/// Create one of the built-in effects by name (see [`NAMES`]), ignoring case. `color` is used
+/// by effects that have one and `period` replaces the default length of one cycle. Returns
+/// [`WootingError::UnknownEffect`] if there is no effect with the name.
+///
+/// ```rust
+/// use wooting_rgb::effect;
+///
+/// assert!(effect::from_name("Rainbow", (255, 255, 255), None).is_ok());
+/// assert!(effect::from_name("disco", (255, 255, 255), None).is_err());
+/// ```
+pub fn from_name(
+    name: &str,
+    color: (u8, u8, u8),
+    period: Option<Duration>,
+) -> Result<Box<dyn Effect + Send>, WootingError> {
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "breathing" => Box::new(Breathing {
+            color,
+            period: period.unwrap_or(Breathing::default().period),
+        }),
+        "rainbow" => Box::new(Rainbow {
+            period: period.unwrap_or(Rainbow::default().period),
+            ..Default::default()
+        }),
+        "spectrum" => Box::new(Spectrum {
+            period: period.unwrap_or(Spectrum::default().period),
+        }),
+        _ => return Err(WootingError::UnknownEffect),
+    })
+}
+
+/// Turn a number of seconds into the length of one cycle of an effect, as given in a request.
+/// Returns [`WootingError::InvalidPeriod`] unless it is a number of seconds greater than zero.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use wooting_rgb::effect;
+///
+/// assert_eq!(effect::period_from_secs(1.5), Ok(Duration::from_millis(1500)));
+/// assert!(effect::period_from_secs(-1.0).is_err());
+/// assert!(effect::period_from_secs(f64::INFINITY).is_err());
+/// ```
+pub fn period_from_secs(seconds: f64) -> Result<Duration, WootingError> {
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(period) if !period.is_zero() => Ok(period),
+        _ => Err(WootingError::InvalidPeriod),
+    }
+}
+
+/// Run an effect on a device at the given frame rate for as long as `running` returns `true`.
+/// `running` is called before every frame with the time since the effect started. Returns
+/// `false` if the device could not be updated, in which case the effect stops early.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use wooting_rgb::{effect::{self, Rainbow}, RgbKeyboard};
+///
+/// let mut keyboard = RgbKeyboard::open().unwrap();
+/// // Show a rainbow for ten seconds at 30 frames per second.
+/// effect::run(&mut Rainbow::default(), &mut keyboard, 30, |elapsed| {
+///     elapsed < Duration::from_secs(10)
+/// });
+/// ```
+pub fn run<E, D, F>(effect: &mut E, device: &mut D, fps: u32, running: F) -> bool
+where
+    E: Effect + ?Sized,
+    D: RgbDevice + ?Sized,
+    F: FnMut(Duration) -> bool,
+{
+    run_paced(effect, device, &mut FramePacer::new(fps), running)
+}
+
+/// Run an effect like [`run`], rendering a frame whenever `pacer` has a slot for one. The
+/// pacer counts the frames that went out late because rendering or updating took too long.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use wooting_rgb::{effect::{self, Rainbow}, pacing::FramePacer, RgbKeyboard};
+///
+/// let mut keyboard = RgbKeyboard::open().unwrap();
+/// let mut pacer = FramePacer::new(60);
+/// effect::run_paced(&mut Rainbow::default(), &mut keyboard, &mut pacer, |elapsed| {
+///     elapsed < Duration::from_secs(10)
+/// });
+/// println!("{} frames were late", pacer.stats().late);
+/// ```
+pub fn run_paced<E, D, F>(
+    effect: &mut E,
+    device: &mut D,
+    pacer: &mut FramePacer,
+    mut running: F,
+) -> bool
+where
+    E: Effect + ?Sized,
+    D: RgbDevice + ?Sized,
+    F: FnMut(Duration) -> bool,
+{
+    let start = Instant::now();
+    let mut frame = Frame::default();
+    loop {
+        pacer.wait();
+        let elapsed = start.elapsed();
+        if !running(elapsed) {
+            return true;
+        }
+
+        effect.render(elapsed, &mut frame);
+        device.array_set_frame(&frame);
+        if pacer.submit() && !device.array_update() {
+            return false;
+        }
+    }
+}
+
+/// Convert a color from hue (`0.0..1.0`), saturation and value (`0.0..=1.0`) to RGB.
+///
+/// ```rust
+/// use wooting_rgb::effect::hsv_to_rgb;
+///
+/// assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), (255, 0, 0));
+/// assert_eq!(hsv_to_rgb(1.0 / 3.0, 1.0, 1.0), (0, 255, 0));
+/// ```
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let hue = hue.rem_euclid(1.0) * 6.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (red, green, blue) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let offset = value - chroma;
+    let component = |c: f32| ((c + offset) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (component(red), component(green), component(blue))
+}
+
+/// Fades the whole keyboard in and out of a color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Breathing {
+    /// The color at full brightness.
+    pub color: (u8, u8, u8),
+    /// How long one full breath, in and out, takes.
+    pub period: Duration,
+}
+
+impl Default for Breathing {
+    fn default() -> Self {
+        Self {
+            color: (255, 255, 255),
+            period: Duration::from_secs(4),
+        }
+    }
+}
+
+impl Effect for Breathing {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let phase = elapsed.as_secs_f32() / self.period.as_secs_f32().max(f32::EPSILON);
+        let brightness = (1.0 - (phase * TAU).cos()) / 2.0;
+        let (red, green, blue) = self.color;
+        let scale = |component: u8| (component as f32 * brightness).round() as u8;
+        frame.fill((scale(red), scale(green), scale(blue)));
+    }
+}
+
+/// A rainbow that moves across the keyboard from left to right.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rainbow {
+    /// How long it takes for a color to travel the width of one rainbow.
+    pub period: Duration,
+    /// How many rainbows fit across the width of the keyboard.
+    pub repeats: f32,
+}
+
+impl Default for Rainbow {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_secs(3),
+            repeats: 1.0,
+        }
+    }
+}
+
+impl Effect for Rainbow {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let phase = elapsed.as_secs_f32() / self.period.as_secs_f32().max(f32::EPSILON);
+        for key in Key::ALL {
+            let (x, _, width, _) = key.physical_rect();
+            let position = (x + width / 2.0) / LAYOUT_WIDTH * self.repeats;
+            frame.set(key, hsv_to_rgb(position - phase, 1.0, 1.0));
+        }
+    }
+}
+
+/// Cycles the whole keyboard through every hue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spectrum {
+    /// How long one full cycle through every hue takes.
+    pub period: Duration,
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Effect for Spectrum {
+    fn render(&mut self, elapsed: Duration, frame: &mut Frame) {
+        let phase = elapsed.as_secs_f32() / self.period.as_secs_f32().max(f32::EPSILON);
+        frame.fill(hsv_to_rgb(phase, 1.0, 1.0));
+    }
+}
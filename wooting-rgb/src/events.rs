@@ -0,0 +1,315 @@
+//! Get told when the keyboard is plugged in, unplugged or swapped for another one, instead of
+//! polling for it.
+//!
+//! Any number of listeners can [`subscribe`], each for as long as its [`Subscription`] is kept,
+//! and [`channel`] delivers the events to an [`EventReceiver`] instead. While anyone listens, a
+//! thread checks the keyboard whenever a Wooting device is plugged in or unplugged on Linux, as
+//! the [`hotplug`](crate::hotplug) monitor reports, and twice a second elsewhere. A failed read
+//! reports a disconnect right away.
+//!
+//! ```rust,no_run
+//! use wooting_rgb::events::{self, ConnectionEvent};
+//!
+//! let mut disconnects = 0;
+//! let subscription = events::subscribe(move |event| {
+//!     if let ConnectionEvent::Disconnected = event {
+//!         disconnects += 1;
+//!         println!("Disconnected {disconnects} times");
+//!     }
+//! });
+//!
+//! for event in events::channel() {
+//!     println!("{event:?}");
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, RecvError, RecvTimeoutError, TryRecvError},
+        Arc, Mutex, Once, PoisonError,
+    },
+    thread::{self, sleep},
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+use crate::hotplug::HotplugMonitor;
+use crate::{device_info, sdk, DeviceInfo};
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// How long a device that was plugged in gets to become ready, checked once after the first
+/// delay and again after the second.
+#[cfg(target_os = "linux")]
+const SETTLE_DELAYS: [Duration; 2] = [Duration::from_millis(250), Duration::from_millis(1000)];
+
+/// A change in which keyboard is connected.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ConnectionEvent {
+    /// A keyboard was plugged in.
+    Connected(DeviceInfo),
+    /// The keyboard was unplugged, or can't be reached anymore.
+    Disconnected,
+    /// Another keyboard took the place of the connected one, such as when one is unplugged and
+    /// another one plugged in between two checks.
+    DeviceChanged(DeviceInfo),
+}
+
+type Listener = Arc<dyn Fn(&ConnectionEvent) -> bool + Send + Sync>;
+
+struct Registry {
+    listeners: BTreeMap<u64, Listener>,
+    next_id: u64,
+    /// The keyboard connected when last checked, if it has been checked.
+    state: Option<Option<DeviceInfo>>,
+    watching: bool,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    listeners: BTreeMap::new(),
+    next_id: 0,
+    state: None,
+    watching: false,
+});
+
+/// Keeps a listener subscribed. Dropping it unsubscribes the listener.
+#[must_use = "the listener is unsubscribed when the subscription is dropped"]
+#[derive(Debug)]
+pub struct Subscription {
+    id: u64,
+}
+
+impl Subscription {
+    /// Stop listening. This is the same as dropping the subscription.
+    pub fn unsubscribe(self) {}
+
+    /// Keep listening for as long as the program runs.
+    pub fn detach(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        REGISTRY.lock().unwrap().listeners.remove(&self.id);
+    }
+}
+
+/// Call `listener` with every event until the returned [`Subscription`] is dropped. Events are
+/// delivered on the thread that noticed them, one at a time. A listener that takes long holds
+/// up the ones after it, so it should hand slow work such as USB writes to another thread. A
+/// listener that panics keeps getting the events after, and doesn't keep the others from them.
+pub fn subscribe<F: FnMut(&ConnectionEvent) + Send + 'static>(listener: F) -> Subscription {
+    let listener = Mutex::new(listener);
+    let id = add(move |event| {
+        (listener.lock().unwrap_or_else(PoisonError::into_inner))(event);
+        true
+    });
+    Subscription { id }
+}
+
+/// Receive every event until the returned [`EventReceiver`] is dropped.
+pub fn channel() -> EventReceiver {
+    let (sender, receiver) = mpsc::channel();
+    let id = add(move |event| sender.send(event.clone()).is_ok());
+    EventReceiver {
+        receiver,
+        _subscription: Subscription { id },
+    }
+}
+
+/// Receives the events from [`channel`]. Dropping it unsubscribes right away, rather than
+/// when the next event can't be delivered. Iterating over it waits for the next event forever.
+#[derive(Debug)]
+pub struct EventReceiver {
+    receiver: Receiver<ConnectionEvent>,
+    _subscription: Subscription,
+}
+
+impl EventReceiver {
+    /// Wait for the next event. See [`Receiver::recv`].
+    pub fn recv(&self) -> Result<ConnectionEvent, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Return the next event if there is one already. See [`Receiver::try_recv`].
+    pub fn try_recv(&self) -> Result<ConnectionEvent, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Wait for the next event for at most `timeout`. See [`Receiver::recv_timeout`].
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<ConnectionEvent, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// Iterate over the events as they come, waiting for each. See [`Receiver::iter`].
+    pub fn iter(&self) -> mpsc::Iter<'_, ConnectionEvent> {
+        self.receiver.iter()
+    }
+
+    /// Iterate over the events that came already. See [`Receiver::try_iter`].
+    pub fn try_iter(&self) -> mpsc::TryIter<'_, ConnectionEvent> {
+        self.receiver.try_iter()
+    }
+}
+
+impl Iterator for EventReceiver {
+    type Item = ConnectionEvent;
+
+    fn next(&mut self) -> Option<ConnectionEvent> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl<'a> IntoIterator for &'a EventReceiver {
+    type Item = ConnectionEvent;
+    type IntoIter = mpsc::Iter<'a, ConnectionEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Add a listener that stays subscribed for as long as it returns `true`, and start watching
+/// the keyboard if no one did yet.
+fn add<F: Fn(&ConnectionEvent) -> bool + Send + Sync + 'static>(listener: F) -> u64 {
+    static DISCONNECTED_CALLBACK: Once = Once::new();
+    DISCONNECTED_CALLBACK.call_once(|| {
+        sdk::lock().set_disconnected_cb(disconnected_callback_handler);
+    });
+
+    let mut registry = REGISTRY.lock().unwrap();
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.listeners.insert(id, Arc::new(listener));
+    if !registry.watching {
+        registry.watching = true;
+        thread::spawn(watch);
+    }
+    id
+}
+
+/// Check the keyboard until no one listens anymore.
+fn watch() {
+    #[cfg(target_os = "linux")]
+    let mut monitor = HotplugMonitor::new().ok();
+    update(device_info().ok());
+    while listening() {
+        #[cfg(target_os = "linux")]
+        if let Some(monitor) = &mut monitor {
+            if let Ok(Some(_)) = monitor.next_event_timeout(Some(WATCH_INTERVAL)) {
+                for delay in SETTLE_DELAYS {
+                    sleep(delay);
+                    update(device_info().ok());
+                }
+            }
+            continue;
+        }
+        sleep(WATCH_INTERVAL);
+        update(device_info().ok());
+    }
+}
+
+/// Is anyone listening? Once no one is, the watching stops.
+fn listening() -> bool {
+    let mut registry = REGISTRY.lock().unwrap();
+    if registry.listeners.is_empty() {
+        registry.watching = false;
+        registry.state = None;
+    }
+    registry.watching
+}
+
+/// The trampoline handed to the SDK, which calls it when a read fails. The SDK is still in use
+/// by the call that failed, which listeners such as the one restoring the colors would wait for,
+/// so they are told on another thread.
+extern "C" fn disconnected_callback_handler() {
+    thread::spawn(|| update(None));
+}
+
+/// Tell every listener how the connected keyboard changed since it was last checked.
+fn update(connected: Option<DeviceInfo>) {
+    let (event, listeners) = {
+        let mut registry = REGISTRY.lock().unwrap();
+        let Some(previous) = registry.state.replace(connected.clone()) else {
+            // The first check only establishes what is connected.
+            return;
+        };
+        let event = match (previous, connected) {
+            (None, Some(info)) => ConnectionEvent::Connected(info),
+            (Some(_), None) => ConnectionEvent::Disconnected,
+            (Some(previous), Some(info)) if previous != info => {
+                ConnectionEvent::DeviceChanged(info)
+            }
+            _ => return,
+        };
+        let listeners: Vec<_> = registry
+            .listeners
+            .iter()
+            .map(|(&id, listener)| (id, listener.clone()))
+            .collect();
+        (event, listeners)
+    };
+
+    // Listeners are called without holding the registry, so they can subscribe and
+    // unsubscribe themselves.
+    for (id, listener) in listeners {
+        // A listener that panics would otherwise end the watching for everyone.
+        let keep = panic::catch_unwind(AssertUnwindSafe(|| listener(&event))).unwrap_or(true);
+        if !keep {
+            REGISTRY.lock().unwrap().listeners.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_unsubscribes_on_drop() {
+        let receiver = channel();
+        let id = receiver._subscription.id;
+        assert!(REGISTRY.lock().unwrap().listeners.contains_key(&id));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        drop(receiver);
+        assert!(!REGISTRY.lock().unwrap().listeners.contains_key(&id));
+    }
+
+    #[test]
+    fn delivery() {
+        let info = |model: &str| DeviceInfo {
+            model: model.into(),
+            device_type: crate::DeviceType::Keyboard,
+            layout: crate::DeviceLayout::Ansi,
+            max_rows: 6,
+            max_columns: 21,
+        };
+        let panicking = subscribe(|_| panic!("listener panicked"));
+        let first = channel();
+        let second = channel();
+
+        // Keep the watching thread from checking the keyboard in between.
+        let _sdk = sdk::lock();
+        // Whatever was checked before, the keyboard is now known to be unplugged.
+        update(None);
+        update(None);
+        first.try_iter().for_each(drop);
+        second.try_iter().for_each(drop);
+
+        update(Some(info("One")));
+        update(Some(info("One")));
+        update(Some(info("Two")));
+        update(None);
+        let expected = [
+            ConnectionEvent::Connected(info("One")),
+            ConnectionEvent::DeviceChanged(info("Two")),
+            ConnectionEvent::Disconnected,
+        ];
+        assert_eq!(first.try_iter().collect::<Vec<_>>(), expected);
+        assert_eq!(second.try_iter().collect::<Vec<_>>(), expected);
+        drop(panicking);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{IntoMatrixRowColumn, Key, COLUMNS, COMPONENTS, ROWS};
+
+/// A complete set of colors for every position in the keyboard matrix. Frames are built up
+/// on the Rust side and can then be sent to the keyboard in one go with
+/// [`RgbKeyboard::array_set_frame`](crate::RgbKeyboard::array_set_frame).
+///
+/// ```rust
+/// use wooting_rgb::{Frame, Key};
+///
+/// let mut frame = Frame::default();
+/// // Set the A key to red..
+/// frame.set(Key::A, (255, 0, 0));
+/// // ..and read it back.
+/// assert_eq!(frame.get(Key::A), Some((255, 0, 0)));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Frame {
+    colors: [[(u8, u8, u8); COLUMNS]; ROWS],
+}
+
+impl Frame {
+    /// Create a frame with every position set to the same color.
+    pub fn filled(color: (u8, u8, u8)) -> Self {
+        Self {
+            colors: [[color; COLUMNS]; ROWS],
+        }
+    }
+
+    /// Return the color at the position of the given key, or `None` if the position is outside
+    /// of the matrix.
+    pub fn get<K: IntoMatrixRowColumn>(&self, key: K) -> Option<(u8, u8, u8)> {
+        let (row, column) = key.get_matrix_row_and_column();
+        self.colors
+            .get(row as usize)
+            .and_then(|row| row.get(column as usize))
+            .copied()
+    }
+
+    /// Set the color at the position of the given key. Returns `false` if the position is
+    /// outside of the matrix, in which case the frame is left untouched.
+    pub fn set<K: IntoMatrixRowColumn>(&mut self, key: K, color: (u8, u8, u8)) -> bool {
+        let (row, column) = key.get_matrix_row_and_column();
+        match self
+            .colors
+            .get_mut(row as usize)
+            .and_then(|row| row.get_mut(column as usize))
+        {
+            Some(slot) => {
+                *slot = color;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set every position in the frame to the same color.
+    pub fn fill(&mut self, color: (u8, u8, u8)) {
+        self.colors = [[color; COLUMNS]; ROWS];
+    }
+
+    /// Iterate over every key on the keyboard along with its color in this frame.
+    pub fn iter(&self) -> impl Iterator<Item = (Key, (u8, u8, u8))> + '_ {
+        Key::ALL.into_iter().map(move |key| {
+            let (row, column) = key.get_matrix_row_and_column();
+            (key, self.colors[row as usize][column as usize])
+        })
+    }
+
+    /// Iterate over every position in the matrix whose color differs from the one in `previous`,
+    /// along with its color in this frame. Positions without a [`Key`] are included as well.
+    ///
+    /// ```rust
+    /// use wooting_rgb::{Frame, IntoMatrixRowColumn, Key};
+    ///
+    /// let previous = Frame::default();
+    /// let mut frame = previous;
+    /// frame.set(Key::A, (255, 0, 0));
+    /// let changes: Vec<_> = frame.diff(&previous).collect();
+    /// assert_eq!(changes, [(Key::A.get_matrix_row_and_column(), (255, 0, 0))]);
+    /// ```
+    pub fn diff<'a>(
+        &'a self,
+        previous: &'a Frame,
+    ) -> impl Iterator<Item = ((u8, u8), (u8, u8, u8))> + 'a {
+        (0..ROWS).flat_map(move |row| {
+            (0..COLUMNS).filter_map(move |column| {
+                let color = self.colors[row][column];
+                (color != previous.colors[row][column])
+                    .then_some(((row as u8, column as u8), color))
+            })
+        })
+    }
+
+    /// Return the colors of every key as an array suitable for
+    /// [`RgbKeyboard::array_set_full`](crate::RgbKeyboard::array_set_full).
+    pub fn to_array(&self) -> Vec<(Key, (u8, u8, u8))> {
+        self.iter().collect()
+    }
+
+    /// Flatten the frame into the buffer layout expected by the C SDK, that is rows of columns
+    /// of red, green and blue components.
+    pub fn to_buffer(&self) -> [u8; COMPONENTS * COLUMNS * ROWS] {
+        let mut buffer = [0; COMPONENTS * COLUMNS * ROWS];
+        for (row, columns) in self.colors.iter().enumerate() {
+            for (column, (red, green, blue)) in columns.iter().enumerate() {
+                let index = row * (COLUMNS * COMPONENTS) + column * COMPONENTS;
+                buffer[index] = *red;
+                buffer[index + 1] = *green;
+                buffer[index + 2] = *blue;
+            }
+        }
+        buffer
+    }
+}
@@ -0,0 +1,250 @@
+//! Notice Wooting devices being plugged in and unplugged on Linux, from the events the kernel
+//! sends over netlink, without talking to the devices.
+//!
+//! The [connection events](crate::events) use this to learn about changes as they happen.
+
+use std::{
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    time::Duration,
+};
+
+use crate::usb;
+
+/// The multicast group the kernel sends its uevents to.
+const KERNEL_EVENTS: u32 = 1;
+
+/// A Wooting device being plugged in or unplugged.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HotplugEvent {
+    Added { vendor_id: u16, product_id: u16 },
+    Removed { vendor_id: u16, product_id: u16 },
+}
+
+/// Listens for Wooting devices being plugged in and unplugged.
+///
+/// ```rust,no_run
+/// use wooting_rgb::hotplug::HotplugMonitor;
+///
+/// let mut monitor = HotplugMonitor::new().unwrap();
+/// loop {
+///     println!("{:?}", monitor.next_event().unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct HotplugMonitor {
+    socket: OwnedFd,
+}
+
+impl HotplugMonitor {
+    /// Start listening. Only devices plugged in or unplugged from now on are reported.
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = KERNEL_EVENTS;
+        let bound = unsafe {
+            libc::bind(
+                fd,
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { socket })
+    }
+
+    /// Wait for the next Wooting device to be plugged in or unplugged.
+    pub fn next_event(&mut self) -> io::Result<HotplugEvent> {
+        loop {
+            if let Some(event) = self.next_event_timeout(None)? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the next Wooting device to be plugged in or unplugged, or
+    /// forever if there is no timeout. Returns `None` if the time ran out.
+    pub fn next_event_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> io::Result<Option<HotplugEvent>> {
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().try_into().unwrap_or(libc::c_int::MAX)
+        });
+        let mut buffer = [0; 8192];
+        loop {
+            let mut poll = libc::pollfd {
+                fd: self.socket.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ready = unsafe { libc::poll(&mut poll, 1, timeout) };
+            if ready < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            if ready == 0 {
+                return Ok(None);
+            }
+
+            let read = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if read < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            if let Some(event) = parse(&buffer[..read as usize]) {
+                return Ok(Some(event));
+            }
+        }
+    }
+}
+
+/// Read a uevent, which is a header followed by `KEY=value` pairs, each ending in a nul. Only
+/// USB devices made by Wooting being added or removed are of interest.
+fn parse(message: &[u8]) -> Option<HotplugEvent> {
+    // A message cut off by a short read ends in the middle of a field, which is left out.
+    let end = message.iter().rposition(|&byte| byte == 0)?;
+    let mut action = None;
+    let mut product = None;
+    let mut is_device = false;
+    for field in message[..end].split(|&byte| byte == 0) {
+        let field = std::str::from_utf8(field).ok()?;
+        match field.split_once('=') {
+            Some(("ACTION", value)) => action = Some(value),
+            Some(("DEVTYPE", value)) => is_device = value == "usb_device",
+            // The IDs are in hexadecimal, followed by the device release.
+            Some(("PRODUCT", value)) => {
+                let mut ids = value.split('/');
+                let vendor_id = u16::from_str_radix(ids.next()?, 16).ok()?;
+                let product_id = u16::from_str_radix(ids.next()?, 16).ok()?;
+                product = Some((vendor_id, product_id));
+            }
+            _ => {}
+        }
+    }
+
+    let (vendor_id, product_id) = product
+        .filter(|&(vendor_id, product_id)| is_device && usb::is_wooting(vendor_id, product_id))?;
+    match action? {
+        "add" => Some(HotplugEvent::Added {
+            vendor_id,
+            product_id,
+        }),
+        "remove" => Some(HotplugEvent::Removed {
+            vendor_id,
+            product_id,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A uevent for a USB device, the way the kernel sends it.
+    fn uevent(action: &str, devtype: &str, product: &str) -> Vec<u8> {
+        let fields = [
+            &format!("{action}@/devices/pci0000:00/0000:00:14.0/usb1/1-2"),
+            &format!("ACTION={action}"),
+            "DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-2",
+            "SUBSYSTEM=usb",
+            &format!("DEVTYPE={devtype}"),
+            &format!("PRODUCT={product}"),
+            "SEQNUM=4242",
+        ];
+        fields
+            .iter()
+            .flat_map(|field| [field.as_bytes(), b"\0"].concat())
+            .collect()
+    }
+
+    #[test]
+    fn wooting() {
+        assert_eq!(
+            parse(&uevent("add", "usb_device", "31e3/1402/100")),
+            Some(HotplugEvent::Added {
+                vendor_id: 0x31e3,
+                product_id: 0x1402,
+            })
+        );
+        assert_eq!(
+            parse(&uevent("remove", "usb_device", "31e3/1402/100")),
+            Some(HotplugEvent::Removed {
+                vendor_id: 0x31e3,
+                product_id: 0x1402,
+            })
+        );
+        // An original Wooting One, under the vendor ID of its microcontroller.
+        assert_eq!(
+            parse(&uevent("add", "usb_device", "3eb/ff01/1")),
+            Some(HotplugEvent::Added {
+                vendor_id: 0x03eb,
+                product_id: 0xff01,
+            })
+        );
+    }
+
+    #[test]
+    fn ignored() {
+        // Other vendors, including other devices with the same microcontroller.
+        assert_eq!(parse(&uevent("add", "usb_device", "46d/c52b/1211")), None);
+        assert_eq!(parse(&uevent("add", "usb_device", "3eb/2ff4/0")), None);
+        // The interfaces of a Wooting device, which come along with the device itself.
+        assert_eq!(
+            parse(&uevent("add", "usb_interface", "31e3/1402/100")),
+            None
+        );
+        // Actions other than plugging in and unplugging.
+        assert_eq!(parse(&uevent("bind", "usb_device", "31e3/1402/100")), None);
+        assert_eq!(
+            parse(&uevent("change", "usb_device", "31e3/1402/100")),
+            None
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let message = uevent("add", "usb_device", "31e3/1402/100");
+        // Cut off anywhere before the product ID is complete.
+        let end = message
+            .windows(4)
+            .position(|window| window == b"/100")
+            .unwrap();
+        for length in 0..end {
+            assert_eq!(parse(&message[..length]), None, "{length}");
+        }
+        assert_eq!(parse(&uevent("add", "usb_device", "31e3")), None);
+        assert_eq!(parse(&uevent("add", "usb_device", "wooting/1402")), None);
+        assert_eq!(parse(&uevent("add", "usb_device", "131e3/1402/100")), None);
+        assert_eq!(parse(b"\xff\xfe\0ACTION=add\0"), None);
+        assert_eq!(parse(b""), None);
+    }
+}
@@ -0,0 +1,419 @@
+//! A small HTTP API for controlling the keyboard from scripts and web pages, with a WebSocket
+//! that streams the shown colors for live previews. Enabled by the `http` feature.
+//!
+//! Colors are given as strings understood by [`parse_color`](crate::parse_color) or as
+//! `[red, green, blue]`, and keys by name. Every response is JSON.
+//!
+//! | Request           | Body                                        | Does                           |
+//! |-------------------|---------------------------------------------|--------------------------------|
+//! | `GET /info`       |                                             | Describe the keyboard          |
+//! | `GET /frame`      |                                             | List the color of each key     |
+//! | `PUT /frame`      | `{"fill": "black", "keys": {"Esc": "red"}}` | Set every key                  |
+//! | `PUT /keys/{key}` | `{"color": "red"}`                          | Set one key                    |
+//! | `GET /effect`     |                                             | Tell whether an effect runs    |
+//! | `PUT /effect`     | `{"name": "rainbow", "period": 2.5}`        | Run a built-in effect          |
+//! | `DELETE /effect`  |                                             | Stop the running effect        |
+//! | `POST /reset`     |                                             | Restore the original colors    |
+//! | `GET /frames`     |                                             | Stream frames over a WebSocket |
+//!
+//! ```sh
+//! curl -X PUT localhost:8742/keys/Esc -d '{"color": "red"}'
+//! ```
+//!
+//! Requests that carry an `Origin` header, which browsers add to requests made by web pages,
+//! are refused with `403` unless the page was served from the same machine, so other sites a
+//! browser has open can't change the colors.
+
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+use crate::{
+    control::{Controller, SetFrame, StartEffect},
+    device_info, is_wooting_keyboard_connected, Color, Frame, Key, RgbDevice, WootingError,
+};
+
+/// The address the server listens on by default, which only accepts local connections.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8742";
+
+const STREAM_INTERVAL: Duration = Duration::from_millis(33);
+/// How long the stream may go without sending anything before a ping is sent, which fails
+/// once the client has gone away.
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Serves the HTTP API for a device.
+///
+/// ```rust,no_run
+/// use wooting_rgb::{http::HttpServer, RgbKeyboard};
+///
+/// let server = HttpServer::local(RgbKeyboard::open().unwrap()).unwrap();
+/// println!("Listening on http://{}", server.local_addr());
+/// server.run();
+/// ```
+pub struct HttpServer {
+    server: Server,
+    controller: Arc<Mutex<Controller>>,
+}
+
+impl HttpServer {
+    /// Listen on [`DEFAULT_ADDRESS`].
+    pub fn local<D: RgbDevice + Send + 'static>(device: D) -> io::Result<Self> {
+        Self::bind(DEFAULT_ADDRESS, device)
+    }
+
+    /// Listen on `address`. Anyone who can reach it can change the colors, so only bind to
+    /// other addresses than the loopback one on trusted networks.
+    pub fn bind<A, D>(address: A, device: D) -> io::Result<Self>
+    where
+        A: ToSocketAddrs,
+        D: RgbDevice + Send + 'static,
+    {
+        Ok(Self {
+            server: Server::http(address).map_err(io::Error::other)?,
+            controller: Arc::new(Mutex::new(Controller::new(device))),
+        })
+    }
+
+    /// The address the server is listening on, useful when binding to port `0`.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server
+            .server_addr()
+            .to_ip()
+            .expect("The server listens on TCP")
+    }
+
+    /// Answer requests forever, each on its own thread.
+    pub fn run(&self) {
+        for request in self.server.incoming_requests() {
+            let controller = self.controller.clone();
+            thread::spawn(move || handle(request, &controller));
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetKey {
+    color: Color,
+}
+
+#[derive(Serialize)]
+struct KeyColor {
+    key: String,
+    color: [u8; 3],
+}
+
+/// Describe the colors of a frame, in [`Key::ALL`] order.
+fn frame_json(frame: &Frame) -> Value {
+    let keys: Vec<_> = frame
+        .iter()
+        .map(|(key, (red, green, blue))| KeyColor {
+            key: key.to_string(),
+            color: [red, green, blue],
+        })
+        .collect();
+    json!(keys)
+}
+
+/// A failed request, answered with its status code and an error message.
+#[derive(Debug)]
+struct Error(u16, String);
+
+impl<E: std::error::Error> From<E> for Error {
+    fn from(error: E) -> Self {
+        Error(400, error.to_string())
+    }
+}
+
+fn updated(updated: bool) -> Result<Value, Error> {
+    if updated {
+        Ok(json!({ "ok": true }))
+    } else {
+        Err(Error(503, WootingError::Disconnected.to_string()))
+    }
+}
+
+fn handle(mut request: Request, controller: &Mutex<Controller>) {
+    let origin = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Origin"));
+    if origin.is_some_and(|origin| !is_local_origin(origin.value.as_str())) {
+        let value = json!({ "ok": false, "error": "Requests from other sites are not allowed" });
+        let response = Response::from_string(value.to_string())
+            .with_status_code(403)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+        let _ = request.respond(response);
+        return;
+    }
+
+    if request.method() == &Method::Get && request.url() == "/frames" {
+        stream(request, controller);
+        return;
+    }
+
+    let mut body = String::new();
+    let result = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => route(request.method(), request.url(), &body, controller),
+        Err(error) => Err(Error(400, error.to_string())),
+    };
+    let (status, value) = match result {
+        Ok(value) => (200, value),
+        Err(Error(status, message)) => (status, json!({ "ok": false, "error": message })),
+    };
+    let response = Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+    let _ = request.respond(response);
+}
+
+fn route(
+    method: &Method,
+    url: &str,
+    body: &str,
+    controller: &Mutex<Controller>,
+) -> Result<Value, Error> {
+    let path = url.split('?').next().unwrap_or_default();
+    match (method, path) {
+        (Method::Get, "/info") => Ok(match device_info() {
+            Ok(info) => json!({
+                "connected": true,
+                "model": info.model,
+                "device_type": format!("{:?}", info.device_type),
+                "layout": format!("{:?}", info.layout),
+            }),
+            Err(_) => json!({ "connected": is_wooting_keyboard_connected() }),
+        }),
+        (Method::Get, "/frame") => Ok(frame_json(&controller.lock().unwrap().frame())),
+        (Method::Put, "/frame") => {
+            let request: SetFrame = serde_json::from_str(body)?;
+            let frame = request.apply(Frame::default())?;
+            updated(controller.lock().unwrap().set_frame(&frame))
+        }
+        (Method::Put, path) if path.starts_with("/keys/") => {
+            let name = percent_decode(&path["/keys/".len()..]);
+            let key: Key = name.parse()?;
+            let request: SetKey = serde_json::from_str(body)?;
+            updated(
+                controller
+                    .lock()
+                    .unwrap()
+                    .set_key(key, request.color.parse()?),
+            )
+        }
+        (Method::Get, "/effect") => {
+            Ok(json!({ "running": controller.lock().unwrap().effect_running() }))
+        }
+        (Method::Put, "/effect") => {
+            let request: StartEffect = serde_json::from_str(body)?;
+            let effect = request.effect()?;
+            controller.lock().unwrap().start_effect(effect);
+            Ok(json!({ "ok": true }))
+        }
+        (Method::Delete, "/effect") => {
+            controller.lock().unwrap().stop_effect();
+            Ok(json!({ "ok": true }))
+        }
+        (Method::Post, "/reset") => updated(controller.lock().unwrap().reset()),
+        _ => Err(Error(404, format!("No such endpoint: {method} {path}"))),
+    }
+}
+
+/// Is `origin` a web page served from this machine, such as `http://localhost:3000`?
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, host)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+/// Decode the `%XX` escapes in a path segment, so keys like `Left Shift` can be named.
+fn percent_decode(segment: &str) -> String {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Upgrade the request to a WebSocket and send the shown colors, as `GET /frame` describes
+/// them, every time they change until the client disconnects.
+///
+/// The upgraded connection can't be read from without blocking the stream, so a client that
+/// went away is noticed by sending it pings while the colors don't change.
+fn stream(request: Request, controller: &Mutex<Controller>) {
+    let key = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| derive_accept_key(header.value.as_bytes()));
+    let Some(accept) = key else {
+        let response = Response::from_string("Expected a WebSocket request").with_status_code(400);
+        let _ = request.respond(response);
+        return;
+    };
+
+    let response = Response::empty(101)
+        .with_header(Header::from_bytes("Connection", "Upgrade").unwrap())
+        .with_header(Header::from_bytes("Upgrade", "websocket").unwrap())
+        .with_header(Header::from_bytes("Sec-WebSocket-Accept", accept).unwrap());
+    let stream = request.upgrade("websocket", response);
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    let mut sent = None;
+    let mut last_sent = Instant::now();
+    loop {
+        let frame = controller.lock().unwrap().frame();
+        let message = if sent != Some(frame) {
+            sent = Some(frame);
+            Message::text(frame_json(&frame).to_string())
+        } else if last_sent.elapsed() >= PING_INTERVAL {
+            Message::Ping(Default::default())
+        } else {
+            sleep(STREAM_INTERVAL);
+            continue;
+        };
+        if socket.send(message).is_err() {
+            return;
+        }
+        last_sent = Instant::now();
+        sleep(STREAM_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VirtualKeyboard;
+
+    fn controller() -> (Arc<Mutex<VirtualKeyboard>>, Mutex<Controller>) {
+        let device = Arc::new(Mutex::new(VirtualKeyboard::default()));
+        (device.clone(), Mutex::new(Controller::new(device)))
+    }
+
+    fn status(result: Result<Value, Error>) -> u16 {
+        match result {
+            Ok(_) => 200,
+            Err(Error(status, _)) => status,
+        }
+    }
+
+    #[test]
+    fn frame() {
+        let (device, controller) = controller();
+        let body = r#"{"fill": "blue", "keys": {"Esc": "red", "A": [0, 255, 0]}}"#;
+        route(&Method::Put, "/frame", body, &controller).unwrap();
+        let frame = device.lock().unwrap().frame();
+        assert_eq!(frame.get(Key::Escape), Some((255, 0, 0)));
+        assert_eq!(frame.get(Key::A), Some((0, 255, 0)));
+        assert_eq!(frame.get(Key::B), Some((0, 0, 255)));
+
+        let value = route(&Method::Get, "/frame", "", &controller).unwrap();
+        let keys = value.as_array().unwrap();
+        assert_eq!(keys.len(), Key::ALL.len());
+        assert_eq!(keys[0], json!({ "key": "Esc", "color": [255, 0, 0] }));
+    }
+
+    #[test]
+    fn keys() {
+        let (device, controller) = controller();
+        let body = r#"{"color": "red"}"#;
+        route(&Method::Put, "/keys/Left%20Shift", body, &controller).unwrap();
+        route(&Method::Put, "/keys/Esc?quiet", body, &controller).unwrap();
+        let frame = device.lock().unwrap().frame();
+        assert_eq!(frame.get(Key::LeftShift), Some((255, 0, 0)));
+        assert_eq!(frame.get(Key::Escape), Some((255, 0, 0)));
+
+        assert_eq!(
+            status(route(&Method::Put, "/keys/Nope", body, &controller)),
+            400
+        );
+        let body = r#"{"color": "chartreuse"}"#;
+        assert_eq!(
+            status(route(&Method::Put, "/keys/A", body, &controller)),
+            400
+        );
+    }
+
+    #[test]
+    fn effect() {
+        let (_, controller) = controller();
+        let running = |controller| route(&Method::Get, "/effect", "", controller).unwrap();
+        assert_eq!(running(&controller), json!({ "running": false }));
+
+        let body = r#"{"name": "rainbow", "period": 2.5}"#;
+        route(&Method::Put, "/effect", body, &controller).unwrap();
+        assert_eq!(running(&controller), json!({ "running": true }));
+        route(&Method::Delete, "/effect", "", &controller).unwrap();
+        assert_eq!(running(&controller), json!({ "running": false }));
+
+        for body in [
+            r#"{"name": "disco"}"#,
+            r#"{"name": "rainbow", "period": -1}"#,
+            r#"{"name": "rainbow", "period": 0}"#,
+        ] {
+            let result = route(&Method::Put, "/effect", body, &controller);
+            assert_eq!(status(result), 400);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let (_, controller) = controller();
+        assert_eq!(status(route(&Method::Put, "/frame", "{", &controller)), 400);
+        assert_eq!(status(route(&Method::Get, "/nope", "", &controller)), 404);
+        assert_eq!(status(route(&Method::Post, "/frame", "", &controller)), 404);
+        assert_eq!(status(route(&Method::Post, "/reset", "", &controller)), 200);
+        assert_eq!(status(route(&Method::Get, "/info", "", &controller)), 200);
+    }
+
+    #[test]
+    fn origins() {
+        for origin in [
+            "http://localhost",
+            "http://LOCALHOST:3000",
+            "https://127.0.0.1:8742",
+            "http://[::1]:8080",
+        ] {
+            assert!(is_local_origin(origin), "{origin}");
+        }
+        for origin in [
+            "null",
+            "https://example.com",
+            "http://localhost.example.com",
+            "http://192.168.1.2",
+            "http://[::2]",
+        ] {
+            assert!(!is_local_origin(origin), "{origin}");
+        }
+    }
+}
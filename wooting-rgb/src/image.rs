@@ -0,0 +1,150 @@
+//! Map images onto the keyboard by sampling them at the physical position of every key.
+//!
+//! Raw RGB pixel buffers are always supported. Loading and decoding image files (PNG, JPEG)
+//! requires the `image` feature.
+
+#[cfg(feature = "image")]
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use ::image::{DynamicImage, ImageResult};
+
+use crate::{Frame, Key, WootingError, COMPONENTS, LAYOUT_HEIGHT, LAYOUT_WIDTH};
+
+/// How an image is scaled onto the keyboard.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ScaleMode {
+    /// Scale the image so that all of it is visible, keeping its aspect ratio. Keys that are
+    /// not covered by the image are left black.
+    #[default]
+    Fit,
+    /// Scale the image so that it covers the whole keyboard, keeping its aspect ratio. Parts
+    /// of the image that fall outside of the keyboard are cropped.
+    Fill,
+    /// Scale the image to exactly the size of the keyboard, ignoring its aspect ratio.
+    Stretch,
+}
+
+/// Map a buffer of tightly packed 8-bit RGB pixels onto the keyboard. Every key is assigned the
+/// average color of the area of the image it covers. Returns
+/// [`WootingError::InvalidBufferSize`] if the buffer does not hold `width * height` pixels.
+///
+/// ```rust
+/// use wooting_rgb::{image::{self, ScaleMode}, Key};
+///
+/// // A 2x1 image, red on the left and blue on the right..
+/// let pixels = [255, 0, 0, 0, 0, 255];
+/// // ..stretched across the whole keyboard.
+/// let frame = image::frame_from_pixels(&pixels, 2, 1, ScaleMode::Stretch).unwrap();
+/// assert_eq!(frame.get(Key::Q), Some((255, 0, 0)));
+/// assert_eq!(frame.get(Key::P), Some((0, 0, 255)));
+/// ```
+pub fn frame_from_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    mode: ScaleMode,
+) -> Result<Frame, WootingError> {
+    if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * COMPONENTS {
+        return Err(WootingError::InvalidBufferSize);
+    }
+
+    let (image_width, image_height) = (width as f32, height as f32);
+    let (scale_x, scale_y) = match mode {
+        ScaleMode::Fit => {
+            let scale = (image_width / LAYOUT_WIDTH).max(image_height / LAYOUT_HEIGHT);
+            (scale, scale)
+        }
+        ScaleMode::Fill => {
+            let scale = (image_width / LAYOUT_WIDTH).min(image_height / LAYOUT_HEIGHT);
+            (scale, scale)
+        }
+        ScaleMode::Stretch => (image_width / LAYOUT_WIDTH, image_height / LAYOUT_HEIGHT),
+    };
+    // Center the keyboard on the image.
+    let offset_x = (image_width - LAYOUT_WIDTH * scale_x) / 2.0;
+    let offset_y = (image_height - LAYOUT_HEIGHT * scale_y) / 2.0;
+
+    let mut frame = Frame::default();
+    for key in Key::ALL {
+        let (x, y, key_width, key_height) = key.physical_rect();
+        let color = average_area(
+            pixels,
+            width as usize,
+            height as usize,
+            (
+                offset_x + x * scale_x,
+                offset_y + y * scale_y,
+                key_width * scale_x,
+                key_height * scale_y,
+            ),
+        );
+        frame.set(key, color);
+    }
+    Ok(frame)
+}
+
+/// Map a decoded image onto the keyboard. See [`frame_from_pixels`] for how pixels are
+/// sampled. Any alpha channel is ignored.
+#[cfg(feature = "image")]
+pub fn frame_from_image(image: &DynamicImage, mode: ScaleMode) -> Frame {
+    let rgb = image.to_rgb8();
+    frame_from_pixels(rgb.as_raw(), rgb.width(), rgb.height(), mode)
+        .expect("decoded image buffer always matches its dimensions")
+}
+
+/// Open and decode an image file and map it onto the keyboard. The format is guessed from the
+/// file contents.
+///
+/// ```rust,no_run
+/// use wooting_rgb::{image::{self, ScaleMode}, RgbKeyboard};
+///
+/// let mut keyboard = RgbKeyboard::open().unwrap();
+/// // Load a logo so it covers the whole keyboard..
+/// let frame = image::open("logo.png", ScaleMode::Fill).unwrap();
+/// // ..and show it.
+/// keyboard.array_set_full(&frame.to_array());
+/// keyboard.array_update();
+/// ```
+#[cfg(feature = "image")]
+pub fn open<P: AsRef<Path>>(path: P, mode: ScaleMode) -> ImageResult<Frame> {
+    let image = ::image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    Ok(frame_from_image(&image, mode))
+}
+
+/// Average the colors of every pixel that overlaps the area `(x, y, width, height)`, weighted by
+/// how much of each pixel is covered. Any part of the area outside of the image counts as black.
+fn average_area(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    (x, y, area_width, area_height): (f32, f32, f32, f32),
+) -> (u8, u8, u8) {
+    let area = area_width * area_height;
+    if area <= 0.0 {
+        return (0, 0, 0);
+    }
+
+    let (left, right) = (x.max(0.0), (x + area_width).min(width as f32));
+    let (top, bottom) = (y.max(0.0), (y + area_height).min(height as f32));
+    let mut sums = [0.0f32; COMPONENTS];
+    let mut pixel_y = top.floor() as usize;
+    while (pixel_y as f32) < bottom {
+        let coverage_y = (bottom.min(pixel_y as f32 + 1.0) - top.max(pixel_y as f32)).max(0.0);
+        let mut pixel_x = left.floor() as usize;
+        while (pixel_x as f32) < right {
+            let coverage_x = (right.min(pixel_x as f32 + 1.0) - left.max(pixel_x as f32)).max(0.0);
+            let index = (pixel_y * width + pixel_x) * COMPONENTS;
+            for (sum, component) in sums.iter_mut().zip(&pixels[index..index + COMPONENTS]) {
+                *sum += *component as f32 * coverage_x * coverage_y;
+            }
+            pixel_x += 1;
+        }
+        pixel_y += 1;
+    }
+
+    let [red, green, blue] = sums.map(|sum| (sum / area).round().clamp(0.0, 255.0) as u8);
+    (red, green, blue)
+}
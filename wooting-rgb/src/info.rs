@@ -0,0 +1,146 @@
+use std::ffi::CStr;
+
+use crate::{
+    sdk::{self, Sdk},
+    WootingError,
+};
+
+/// Represents the kind of Wooting device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DeviceType {
+    /// A tenkeyless keyboard, such as the Wooting One.
+    KeyboardTkl,
+    /// A full size keyboard, such as the Wooting Two.
+    Keyboard,
+    /// A 60% keyboard, such as the Wooting 60HE.
+    Keyboard60,
+    /// A three key keypad, such as the Wooting UwU.
+    Keypad3Key,
+    /// An 80% keyboard, such as the Wooting 80HE.
+    Keyboard80,
+    /// A device type this crate doesn't know about yet.
+    Unknown(u32),
+}
+
+impl From<u32> for DeviceType {
+    fn from(device_type: u32) -> Self {
+        match device_type {
+            1 => Self::KeyboardTkl,
+            2 => Self::Keyboard,
+            3 => Self::Keyboard60,
+            4 => Self::Keypad3Key,
+            5 => Self::Keyboard80,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Represents the physical layout of a keyboard.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DeviceLayout {
+    Ansi,
+    Iso,
+    Unknown,
+}
+
+/// Represents information about a connected Wooting device.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DeviceInfo {
+    /// The name of the model, such as `Wooting 80HE`.
+    pub model: String,
+    /// What kind of device it is.
+    pub device_type: DeviceType,
+    /// The physical layout of the keys.
+    pub layout: DeviceLayout,
+    /// How many rows the RGB matrix of the device has.
+    pub max_rows: u8,
+    /// How many columns the RGB matrix of the device has.
+    pub max_columns: u8,
+}
+
+/// How many Wooting devices are connected?
+///
+/// ```rust,no_run
+/// println!("{} Wooting devices connected", wooting_rgb::device_count());
+/// ```
+pub fn device_count() -> u8 {
+    let mut sdk = sdk::lock();
+    // The device list is only refreshed when looking for a keyboard.
+    sdk.kbd_connected();
+    sdk.device_count()
+}
+
+/// Select which of the connected devices, starting at `0`, all further operations apply to.
+/// Returns `true` if the device is selected.
+///
+/// ```rust,no_run
+/// // Send colors to the second keyboard from now on.
+/// wooting_rgb::select_device(1);
+/// ```
+pub fn select_device(index: u8) -> bool {
+    sdk::lock().select_device(index)
+}
+
+/// Make sure a keyboard is connected and can be used. Returns
+/// [`WootingError::PermissionDenied`] if one is connected that this process isn't allowed to
+/// open, which on Linux means the [udev rules](crate::udev) are missing, and
+/// [`WootingError::Disconnected`] if there is none.
+///
+/// ```rust,no_run
+/// if let Err(error) = wooting_rgb::check_connection() {
+///     eprintln!("{error}");
+/// }
+/// ```
+pub fn check_connection() -> Result<(), WootingError> {
+    check(&mut sdk::lock())
+}
+
+/// [`check_connection`], with the SDK already taken.
+fn check(sdk: &mut Sdk) -> Result<(), WootingError> {
+    if sdk.kbd_connected() {
+        return Ok(());
+    }
+    #[cfg(target_os = "linux")]
+    if crate::udev::permission_denied() {
+        return Err(WootingError::PermissionDenied);
+    }
+    Err(WootingError::Disconnected)
+}
+
+/// Return information about the selected device. Fails the way [`check_connection`] does if
+/// there is no device that can be used.
+///
+/// ```rust,no_run
+/// let info = wooting_rgb::device_info().unwrap();
+/// println!("{} ({:?}, {:?})", info.model, info.device_type, info.layout);
+/// ```
+// The bindings use other integer types for the enums on other platforms.
+#[allow(clippy::unnecessary_cast)]
+pub fn device_info() -> Result<DeviceInfo, WootingError> {
+    let mut sdk = sdk::lock();
+    check(&mut sdk)?;
+
+    let meta = sdk
+        .device_info()
+        .filter(|meta| meta.connected)
+        .ok_or(WootingError::Disconnected)?;
+    let model = if meta.model.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(meta.model) }
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    Ok(DeviceInfo {
+        model,
+        device_type: DeviceType::from(meta.device_type as u32),
+        layout: match meta.layout as i32 {
+            0 => DeviceLayout::Ansi,
+            1 => DeviceLayout::Iso,
+            _ => DeviceLayout::Unknown,
+        },
+        max_rows: meta.max_rows,
+        max_columns: meta.max_columns,
+    })
+}
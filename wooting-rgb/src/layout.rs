@@ -0,0 +1,119 @@
+use crate::Key;
+
+/// How wide is the keyboard, in key units?
+pub const LAYOUT_WIDTH: f32 = 17.25;
+/// How tall is the keyboard, in key units?
+pub const LAYOUT_HEIGHT: f32 = 6.25;
+
+impl Key {
+    /// Returns a tuple `(x, y, width, height)` that represents the physical area of the key,
+    /// measured in key units from the top left corner of the keyboard. One key unit is the
+    /// width of a letter key. The whole keyboard spans [`LAYOUT_WIDTH`] by [`LAYOUT_HEIGHT`].
+    ///
+    /// The space bar is split into five equally sized areas, one for each of its LEDs.
+    ///
+    /// ```rust
+    /// use wooting_rgb::Key;
+    ///
+    /// assert_eq!(Key::Escape.physical_rect(), (0.0, 0.0, 1.0, 1.0));
+    /// ```
+    pub fn physical_rect(&self) -> (f32, f32, f32, f32) {
+        use Key::*;
+        let (x, y, width) = match self {
+            Escape => (0.0, 0.0, 1.0),
+            F1 => (1.5, 0.0, 1.0),
+            F2 => (2.5, 0.0, 1.0),
+            F3 => (3.5, 0.0, 1.0),
+            F4 => (4.5, 0.0, 1.0),
+            F5 => (5.75, 0.0, 1.0),
+            F6 => (6.75, 0.0, 1.0),
+            F7 => (7.75, 0.0, 1.0),
+            F8 => (8.75, 0.0, 1.0),
+            F9 => (10.0, 0.0, 1.0),
+            F10 => (11.0, 0.0, 1.0),
+            F11 => (12.0, 0.0, 1.0),
+            F12 => (13.0, 0.0, 1.0),
+            Mode => (14.25, 0.0, 1.0),
+            PrintScreen => (15.25, 0.0, 1.0),
+            Pause => (16.25, 0.0, 1.0),
+
+            Tilde => (0.0, 1.25, 1.0),
+            One => (1.0, 1.25, 1.0),
+            Two => (2.0, 1.25, 1.0),
+            Three => (3.0, 1.25, 1.0),
+            Four => (4.0, 1.25, 1.0),
+            Five => (5.0, 1.25, 1.0),
+            Six => (6.0, 1.25, 1.0),
+            Seven => (7.0, 1.25, 1.0),
+            Eight => (8.0, 1.25, 1.0),
+            Nine => (9.0, 1.25, 1.0),
+            Zero => (10.0, 1.25, 1.0),
+            Dash => (11.0, 1.25, 1.0),
+            Equals => (12.0, 1.25, 1.0),
+            Backspace => (13.0, 1.25, 2.0),
+            Insert => (15.25, 1.25, 1.0),
+            PageUp => (16.25, 1.25, 1.0),
+
+            Tab => (0.0, 2.25, 1.5),
+            Q => (1.5, 2.25, 1.0),
+            W => (2.5, 2.25, 1.0),
+            E => (3.5, 2.25, 1.0),
+            R => (4.5, 2.25, 1.0),
+            T => (5.5, 2.25, 1.0),
+            Y => (6.5, 2.25, 1.0),
+            U => (7.5, 2.25, 1.0),
+            I => (8.5, 2.25, 1.0),
+            O => (9.5, 2.25, 1.0),
+            P => (10.5, 2.25, 1.0),
+            LeftBracket => (11.5, 2.25, 1.0),
+            RightBracket => (12.5, 2.25, 1.0),
+            Backslash => (13.5, 2.25, 1.5),
+            Delete => (15.25, 2.25, 1.0),
+            PageDown => (16.25, 2.25, 1.0),
+
+            CapsLock => (0.0, 3.25, 1.75),
+            A => (1.75, 3.25, 1.0),
+            S => (2.75, 3.25, 1.0),
+            D => (3.75, 3.25, 1.0),
+            F => (4.75, 3.25, 1.0),
+            G => (5.75, 3.25, 1.0),
+            H => (6.75, 3.25, 1.0),
+            J => (7.75, 3.25, 1.0),
+            K => (8.75, 3.25, 1.0),
+            L => (9.75, 3.25, 1.0),
+            SemiColon => (10.75, 3.25, 1.0),
+            Apostrophe => (11.75, 3.25, 1.0),
+            Return => (12.75, 3.25, 2.25),
+
+            LeftShift => (0.0, 4.25, 2.25),
+            Z => (2.25, 4.25, 1.0),
+            X => (3.25, 4.25, 1.0),
+            C => (4.25, 4.25, 1.0),
+            V => (5.25, 4.25, 1.0),
+            B => (6.25, 4.25, 1.0),
+            N => (7.25, 4.25, 1.0),
+            M => (8.25, 4.25, 1.0),
+            Comma => (9.25, 4.25, 1.0),
+            Period => (10.25, 4.25, 1.0),
+            ForwardSlash => (11.25, 4.25, 1.0),
+            RightShift => (12.25, 4.25, 2.75),
+            UpArrow => (15.25, 4.25, 1.0),
+
+            LeftControl => (0.0, 5.25, 1.25),
+            LeftMod => (1.25, 5.25, 1.25),
+            LeftAlt => (2.5, 5.25, 1.25),
+            SpaceLed1 => (3.75, 5.25, 1.25),
+            SpaceLed2 => (5.0, 5.25, 1.25),
+            Space => (6.25, 5.25, 1.25),
+            SpaceLed4 => (7.5, 5.25, 1.25),
+            SpaceLed5 => (8.75, 5.25, 1.25),
+            RightAlt => (10.0, 5.25, 1.25),
+            Fn => (11.25, 5.25, 1.25),
+            RightControl => (12.5, 5.25, 1.25),
+            LeftArrow => (14.25, 5.25, 1.0),
+            DownArrow => (15.25, 5.25, 1.0),
+            RightArrow => (16.25, 5.25, 1.0),
+        };
+        (x, y, width, 1.0)
+    }
+}
@@ -7,13 +7,75 @@
 //! [examples]: https://github.com/ShayBox/Wooting-RGB/tree/master/wooting-rgb/examples
 
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fmt::{self, Display},
-    sync::Mutex,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
 };
 
-use lazy_static::lazy_static;
 use thiserror::Error;
 
+use crate::{
+    events::ConnectionEvent,
+    output::{Dither, OutputStage, RawMask},
+    power::{PowerBudget, PowerReport},
+    sdk::Backend,
+    update::{UpdateMetrics, UpdateStrategy},
+};
+
+#[cfg(feature = "async")]
+pub use crate::asynchronous::AsyncRgbKeyboard;
+pub use crate::{
+    color::{parse_color, Color},
+    device::{RgbDevice, VirtualKeyboard},
+    frame::Frame,
+    info::{
+        check_connection, device_count, device_info, select_device, DeviceInfo, DeviceLayout,
+        DeviceType,
+    },
+    layout::{LAYOUT_HEIGHT, LAYOUT_WIDTH},
+    shared::SharedRgbKeyboard,
+};
+
+pub mod animation;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod color;
+#[cfg(any(feature = "dbus", feature = "http", feature = "mqtt"))]
+mod control;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+mod device;
+pub mod effect;
+pub mod events;
+mod frame;
+#[cfg(target_os = "linux")]
+pub mod hotplug;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod image;
+mod info;
+mod layout;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod openrgb;
+pub mod output;
+pub mod pacing;
+pub mod power;
+pub mod preview;
+pub mod recording;
+mod sdk;
+mod shared;
+pub mod text;
+#[cfg(target_os = "linux")]
+pub mod udev;
+pub mod update;
+pub mod usb;
+
 /// Represents an error that can occur when querying the state of a Wooting keyboard.
 #[derive(Clone, Copy, Debug, Error, Eq, Hash, PartialEq)]
 pub enum WootingError {
@@ -21,6 +83,24 @@ pub enum WootingError {
     Disconnected,
     #[error("Requested analog value of too many keys")]
     InvalidBufferSize,
+    #[error("Unknown key name")]
+    UnknownKey,
+    #[error("Invalid color")]
+    InvalidColor,
+    #[error("Unknown effect name")]
+    UnknownEffect,
+    #[error("Wooting keyboard is already open in this process")]
+    AlreadyOpen,
+    #[error("Not allowed to access the Wooting keyboard, are the udev rules installed?")]
+    PermissionDenied,
+    #[error("Playback speed must be a positive number, and not so small that delays overflow")]
+    InvalidSpeed,
+    #[error("Effect period must be a positive number of seconds")]
+    InvalidPeriod,
+    #[error("Output stage values must be finite, with a gamma above zero and no negative scale")]
+    InvalidOutputStage,
+    #[error("Power budget must be a positive number of milliamps")]
+    InvalidPowerBudget,
 }
 
 /// Types that implement this trait can be transformed into a matrix row and column.
@@ -242,6 +322,103 @@ pub enum Key {
     RightArrow,
 }
 
+impl Key {
+    /// Every key on the keyboard, in matrix order (row by row, left to right).
+    pub const ALL: [Key; 88] = {
+        use Key::*;
+        [
+            Escape,
+            F1,
+            F2,
+            F3,
+            F4,
+            F5,
+            F6,
+            F7,
+            F8,
+            F9,
+            F10,
+            F11,
+            F12,
+            Mode,
+            PrintScreen,
+            Pause,
+            Tilde,
+            One,
+            Two,
+            Three,
+            Four,
+            Five,
+            Six,
+            Seven,
+            Eight,
+            Nine,
+            Zero,
+            Dash,
+            Equals,
+            Backspace,
+            Insert,
+            PageUp,
+            Tab,
+            Q,
+            W,
+            E,
+            R,
+            T,
+            Y,
+            U,
+            I,
+            O,
+            P,
+            LeftBracket,
+            RightBracket,
+            Backslash,
+            Delete,
+            PageDown,
+            CapsLock,
+            A,
+            S,
+            D,
+            F,
+            G,
+            H,
+            J,
+            K,
+            L,
+            SemiColon,
+            Apostrophe,
+            Return,
+            LeftShift,
+            Z,
+            X,
+            C,
+            V,
+            B,
+            N,
+            M,
+            Comma,
+            Period,
+            ForwardSlash,
+            RightShift,
+            UpArrow,
+            LeftControl,
+            LeftMod,
+            LeftAlt,
+            SpaceLed1,
+            SpaceLed2,
+            Space,
+            SpaceLed4,
+            SpaceLed5,
+            RightAlt,
+            Fn,
+            RightControl,
+            LeftArrow,
+            DownArrow,
+            RightArrow,
+        ]
+    };
+}
+
 impl Display for Key {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Key::*;
@@ -342,6 +519,50 @@ impl Display for Key {
     }
 }
 
+impl FromStr for Key {
+    type Err = WootingError;
+
+    /// Parse a key from its name, ignoring case. Both the name the key is displayed with
+    /// (`"Left Shift"`, `"~"`) and the name of its variant (`"LeftShift"`, `"Tilde"`) are
+    /// accepted, as are the characters `" "`, `","` and `"."`.
+    ///
+    /// ```rust
+    /// use wooting_rgb::Key;
+    ///
+    /// assert_eq!("left shift".parse(), Ok(Key::LeftShift));
+    /// assert_eq!("Escape".parse(), Ok(Key::Escape));
+    /// assert_eq!("~".parse(), Ok(Key::Tilde));
+    /// assert_eq!("q".parse(), Ok(Key::Q));
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            " " => return Ok(Key::Space),
+            "," => return Ok(Key::Comma),
+            "." => return Ok(Key::Period),
+            _ => {}
+        }
+
+        // Separators are only ignored in longer names since `-` is also the name of a key.
+        let normalize = |name: &str| -> String {
+            if name.chars().count() > 1 {
+                name.chars()
+                    .filter(|c| !matches!(c, ' ' | '_' | '-'))
+                    .collect()
+            } else {
+                name.to_owned()
+            }
+        };
+        let name = normalize(name);
+        Key::ALL
+            .into_iter()
+            .find(|key| {
+                normalize(&key.to_string()).eq_ignore_ascii_case(&name)
+                    || format!("{key:?}").eq_ignore_ascii_case(&name)
+            })
+            .ok_or(WootingError::UnknownKey)
+    }
+}
+
 impl FromScanIndex for Key {
     /// Return the key that corresponds to the provided scan index, if any.
     fn from_scan_index(index: u8) -> Option<Self> {
@@ -545,9 +766,10 @@ const COLUMNS: usize = 21;
 const ROWS: usize = 6;
 /// How many components are there in a color?
 const COMPONENTS: usize = 3;
-lazy_static! {
-    static ref CALLBACK: Mutex<Option<Box<dyn Fn() + Send>>> = Default::default();
-}
+/// Is there an [`RgbKeyboard`] in this process?
+static OPEN: AtomicBool = AtomicBool::new(false);
+/// The listener set by [`set_disconnected_callback`], if any.
+static DISCONNECTED_CALLBACK: Mutex<Option<events::Subscription>> = Mutex::new(None);
 
 /// Is there a Wooting keyboard connected?
 ///
@@ -556,47 +778,322 @@ lazy_static! {
 /// assert!(wooting_rgb::is_wooting_keyboard_connected());
 /// ```
 pub fn is_wooting_keyboard_connected() -> bool {
-    unsafe { wooting_rgb_sys::wooting_rgb_kbd_connected() }
+    sdk::lock().kbd_connected()
 }
 
-/// This is a trampoline function that is provided to the C function to be invoked which will
-/// in turn invoke the user provided callback. The user provided callback would normally be
-/// stored in userdata but due to the lack of any, we use a static instead.
-extern "C" fn set_disconnected_callback_handler() {
-    if let Some(ref mut callback) = *CALLBACK.lock().unwrap() {
-        callback();
-    } else {
-        panic!("Callback static has not been set");
-    }
-}
-
-/// Set a callback to be invoked when a keyboard is disconnected. Currently only happens on a
-/// failed read.
+/// Set a callback to be invoked when a keyboard is disconnected, replacing the one set before.
 ///
 /// See [`rgb_disconnected_callback`][example] example for usage.
 ///
 /// [example]: https://github.com/shaybox/wooting-rgb/blob/master/wooting-rgb/examples/rgb_disconnected_callback.rs
+#[deprecated(note = "use `events::subscribe`, which supports any number of listeners")]
 pub fn set_disconnected_callback<F: 'static + Fn() + Send>(callback: F) {
-    *CALLBACK.lock().unwrap() = Some(Box::new(callback));
-    unsafe {
-        wooting_rgb_sys::wooting_rgb_set_disconnected_cb(Some(set_disconnected_callback_handler));
+    let subscription = events::subscribe(move |event| {
+        if let ConnectionEvent::Disconnected = event {
+            callback();
+        }
+    });
+    *DISCONNECTED_CALLBACK.lock().unwrap() = Some(subscription);
+}
+
+/// The colors set directly on top of the color array, or `None` for keys that were reset to
+/// their original color.
+type DirectColors = BTreeMap<(u8, u8), Option<DirectColor>>;
+
+/// A color set directly, as the caller set it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct DirectColor {
+    color: (u8, u8, u8),
+    /// Does it bypass the output stage?
+    raw: bool,
+}
+
+/// The lighting state of an [`RgbKeyboard`], taken with [`RgbKeyboard::snapshot`] to go back
+/// to it later with [`RgbKeyboard::restore`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snapshot {
+    array: Frame,
+    raw: RawMask,
+    applied: Option<Frame>,
+    direct: DirectColors,
+    auto_update: bool,
+    pending: bool,
+}
+
+impl Snapshot {
+    /// The color array as it was composed, including changes that weren't applied yet.
+    pub fn frame(&self) -> Frame {
+        self.array
+    }
+
+    /// Was the auto-update trigger set?
+    pub fn auto_update(&self) -> bool {
+        self.auto_update
+    }
+
+    /// The colors that were set directly on top of the color array.
+    pub fn direct_overrides(&self) -> impl Iterator<Item = ((u8, u8), (u8, u8, u8))> + '_ {
+        self.direct
+            .iter()
+            .filter_map(|(&position, &direct)| Some((position, direct?.color)))
+    }
+}
+
+/// Represents the connected keyboard to perform RGB operations. The SDK keeps its state
+/// globally, so there can only be one of these in a process at a time, which resets the
+/// keyboard when it is dropped. Use a [`SharedRgbKeyboard`] to share it between threads.
+#[derive(Debug)]
+pub struct RgbKeyboard {
+    /// What was sent to the keyboard, shared with the listener that restores it.
+    state: Arc<Mutex<KeyboardState>>,
+    /// Restores the keyboard when it is plugged back in, if enabled.
+    reconnect: Option<Restorer>,
+    /// Records the calls instead of making them, in the tests that set it.
+    #[cfg(test)]
+    recorder: Option<tests::Recorder>,
+}
+
+/// Shows the colors again on a thread of its own whenever the keyboard is plugged back in, so
+/// the USB writes don't hold up the other listeners. Dropping it stops that.
+#[derive(Debug)]
+struct Restorer {
+    _subscription: events::Subscription,
+    /// Cleared once dropped, for restores that were already asked for.
+    enabled: Arc<AtomicBool>,
+}
+
+impl Restorer {
+    fn start(state: Arc<Mutex<KeyboardState>>) -> Self {
+        let enabled = Arc::new(AtomicBool::new(true));
+        let (sender, receiver) = mpsc::channel();
+        let still_enabled = enabled.clone();
+        // The thread ends once the subscription is dropped along with the sender.
+        thread::spawn(move || {
+            for () in receiver {
+                let state = state.lock().unwrap();
+                // Checked while holding the state, so a restore can't follow a reset.
+                if !still_enabled.load(Ordering::Acquire) {
+                    break;
+                }
+                state.show(&mut sdk::lock());
+            }
+        });
+        let subscription = events::subscribe(move |event| {
+            if let ConnectionEvent::Connected(_) | ConnectionEvent::DeviceChanged(_) = event {
+                let _ = sender.send(());
+            }
+        });
+        Self {
+            _subscription: subscription,
+            enabled,
+        }
     }
 }
 
-/// Represents the connected keyboard to perform RGB operations. This struct is empty and
-/// only exists to enforce that `reset` is called on drop.
-#[derive(Clone, Debug, Default)]
-pub struct RgbKeyboard;
+impl Drop for Restorer {
+    fn drop(&mut self) {
+        self.enabled.store(false, Ordering::Release);
+    }
+}
+
+/// The parts of the SDK state that are lost when the keyboard is unplugged.
+#[derive(Debug, Default)]
+struct KeyboardState {
+    /// The color array, including changes that haven't been applied yet.
+    array: Frame,
+    /// The positions in the color array that bypass the output stage.
+    raw: RawMask,
+    /// The colors last sent for the color array, after the output stage, if it was applied.
+    applied: Option<Frame>,
+    /// The colors set directly since the color array was last sent.
+    direct: DirectColors,
+    auto_update: bool,
+    /// Were there changes to the color array since it was last applied?
+    pending: bool,
+    strategy: UpdateStrategy,
+    metrics: UpdateMetrics,
+    output: OutputStage,
+    dither: Dither,
+    power: Option<PowerBudget>,
+    /// How the color array was last limited to the power budget, if there is one.
+    power_report: Option<PowerReport>,
+}
 
 impl RgbKeyboard {
+    /// Take ownership of the keyboard. Fails with [`WootingError::AlreadyOpen`] while another
+    /// `RgbKeyboard` exists in this process.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{RgbKeyboard, WootingError};
+    ///
+    /// let keyboard = RgbKeyboard::open().unwrap();
+    /// assert_eq!(RgbKeyboard::open().unwrap_err(), WootingError::AlreadyOpen);
+    /// // Once the keyboard is dropped, it can be opened again.
+    /// drop(keyboard);
+    /// assert!(RgbKeyboard::open().is_ok());
+    /// ```
+    pub fn open() -> Result<Self, WootingError> {
+        OPEN.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| WootingError::AlreadyOpen)?;
+        Ok(Self {
+            state: Default::default(),
+            reconnect: None,
+            #[cfg(test)]
+            recorder: None,
+        })
+    }
+
+    /// Take the SDK for the calls that change the colors.
+    #[cfg(not(test))]
+    fn backend(&self) -> impl Backend {
+        sdk::lock()
+    }
+
+    /// Take the SDK, or the recorder if the test set one.
+    #[cfg(test)]
+    fn backend(&self) -> Box<dyn Backend> {
+        match &self.recorder {
+            Some(recorder) => Box::new(recorder.clone()),
+            None => Box::new(sdk::lock()),
+        }
+    }
+
+    /// Restore the colors when the keyboard is plugged back in or replaced by another one,
+    /// along with the auto-update trigger. The colors last applied with `array_update`, or set
+    /// while auto-updating, are shown again along with the colors set with `direct_set_key`
+    /// since, and changes that weren't applied yet are kept. By default, nothing is restored.
+    ///
+    /// Reconnecting is noticed through [`events`], which checks the keyboard twice a second.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{Frame, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// keyboard.restore_on_reconnect(true);
+    /// keyboard.array_set_frame(&Frame::filled((255, 0, 0)));
+    /// keyboard.array_update();
+    /// // The keyboard turns red again whenever it is unplugged and plugged back in.
+    /// ```
+    pub fn restore_on_reconnect(&mut self, enabled: bool) {
+        if !enabled {
+            self.reconnect = None;
+        } else if self.reconnect.is_none() {
+            self.reconnect = Some(Restorer::start(self.state.clone()));
+        }
+    }
+
+    /// Choose how `array_update` sends the color array. By default, it is sent whole every
+    /// time. See [`update`] for the alternative.
+    pub fn set_update_strategy(&mut self, strategy: UpdateStrategy) {
+        self.state.lock().unwrap().strategy = strategy;
+    }
+
+    /// How many frames were sent to the keyboard since it was opened, and how much color data
+    /// that took.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{update::UpdateStrategy, Frame, Key, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// keyboard.set_update_strategy(UpdateStrategy::PARTIAL);
+    /// keyboard.array_set_frame(&Frame::filled((0, 0, 255)));
+    /// keyboard.array_update();
+    /// keyboard.array_set_single(Key::A, 255, 255, 255);
+    /// keyboard.array_update();
+    ///
+    /// let metrics = keyboard.update_metrics();
+    /// assert_eq!((metrics.full_updates, metrics.partial_updates), (1, 1));
+    /// ```
+    pub fn update_metrics(&self) -> UpdateMetrics {
+        self.state.lock().unwrap().metrics
+    }
+
+    /// Set how colors are corrected before they are sent, see [`output`]. Takes effect the next
+    /// time the color array is applied, or right away while auto-updating. Fails with
+    /// [`WootingError::InvalidOutputStage`] if the stage doesn't pass [`OutputStage::check`],
+    /// leaving the stage as it was.
+    pub fn set_output_stage(&mut self, stage: OutputStage) -> Result<(), WootingError> {
+        stage.check()?;
+        let mut state = self.state.lock().unwrap();
+        state.output = stage;
+        state.refresh(&mut self.backend());
+        Ok(())
+    }
+
+    /// How colors are corrected before they are sent.
+    pub fn output_stage(&self) -> OutputStage {
+        self.state.lock().unwrap().output
+    }
+
+    /// Limit how much current the color array may draw, see [`power`], or remove the limit
+    /// with `None`. Takes effect the next time the color array is applied, or right away while
+    /// auto-updating. Colors set directly with `direct_set_key` aren't limited. By default,
+    /// there is no limit. Fails with [`WootingError::InvalidPowerBudget`] if the budget doesn't
+    /// pass [`PowerBudget::check`], leaving the limit as it was.
+    pub fn set_power_budget(&mut self, budget: Option<PowerBudget>) -> Result<(), WootingError> {
+        if let Some(budget) = &budget {
+            budget.check()?;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.power = budget;
+        state.power_report = None;
+        state.refresh(&mut self.backend());
+        Ok(())
+    }
+
+    /// How the color array was estimated and limited when it was last applied, or `None` if
+    /// there is no power budget or it wasn't applied since the budget was set.
+    pub fn power_report(&self) -> Option<PowerReport> {
+        self.state.lock().unwrap().power_report
+    }
+
+    /// Save the lighting state: the color array including changes that weren't applied yet, the
+    /// colors set directly on top of it and the auto-update trigger. The output stage, power
+    /// budget and update strategy are settings rather than state, so they aren't included.
+    ///
+    /// ```rust,no_run
+    /// use std::{thread::sleep, time::Duration};
+    ///
+    /// use wooting_rgb::{Frame, Key, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// keyboard.array_set_frame(&Frame::filled((0, 0, 255)));
+    /// keyboard.array_update();
+    ///
+    /// // Flash a notification..
+    /// let snapshot = keyboard.snapshot();
+    /// keyboard.array_set_frame(&Frame::filled((255, 0, 0)));
+    /// keyboard.array_update();
+    /// sleep(Duration::from_secs(1));
+    /// // ..and go back to blue.
+    /// keyboard.restore(&snapshot);
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        self.state.lock().unwrap().snapshot()
+    }
+
+    /// Go back to a [`Snapshot`] taken with [`snapshot`](Self::snapshot). The keyboard shows
+    /// what it showed then, and changes that weren't applied then are pending again. If the
+    /// color array wasn't applied then, the keyboard is reset to its original colors first.
+    /// Returns `true` if the colors are restored.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .restore(&mut self.backend(), snapshot)
+    }
+
     /// Set the color of a single key. This will not influence the keyboard color array. Use
     /// this function for simple amplifications, like a notification. Use the array functions
     /// if you want to change the entire keyboard. Returns `true` if the color is set.
     ///
+    /// The color goes through the [output stage](Self::set_output_stage), but isn't limited
+    /// by the [power budget](Self::set_power_budget), which only applies to the color array.
+    ///
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Set the A key to white...
     /// keyboard.direct_set_key(Key::A, 255, 255, 255);
     /// ```
@@ -607,8 +1104,25 @@ impl RgbKeyboard {
         green: u8,
         blue: u8,
     ) -> bool {
-        let (row, column) = key.get_matrix_row_and_column();
-        unsafe { wooting_rgb_sys::wooting_rgb_direct_set_key(row, column, red, green, blue) }
+        self.set_direct(key.get_matrix_row_and_column(), (red, green, blue), false)
+    }
+
+    /// Set the color of a single key like [`direct_set_key`](Self::direct_set_key), bypassing
+    /// the output stage.
+    pub fn direct_set_key_raw<K: IntoMatrixRowColumn>(
+        &mut self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.set_direct(key.get_matrix_row_and_column(), (red, green, blue), true)
+    }
+
+    fn set_direct(&mut self, row_and_column: (u8, u8), color: (u8, u8, u8), raw: bool) -> bool {
+        let direct = DirectColor { color, raw };
+        let mut state = self.state.lock().unwrap();
+        state.set_direct(&mut self.backend(), row_and_column, Some(direct))
     }
 
     /// Directly reset the color of a single key on the keyboard. This will not influence the
@@ -619,31 +1133,74 @@ impl RgbKeyboard {
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Set the A key to white...
     /// keyboard.direct_set_key(Key::A, 255, 255, 255);
     /// // ..and then reset it back!
     /// keyboard.direct_reset_key(Key::A);
     /// ```
     pub fn direct_reset_key<K: IntoMatrixRowColumn>(&mut self, key: K) -> bool {
-        let (row, column) = key.get_matrix_row_and_column();
-        unsafe { wooting_rgb_sys::wooting_rgb_direct_reset_key(row, column) }
+        let row_and_column = key.get_matrix_row_and_column();
+        let mut state = self.state.lock().unwrap();
+        state.set_direct(&mut self.backend(), row_and_column, None)
+    }
+
+    /// The color of a key as it was last set: the color set directly on top of the color array
+    /// if there is one, or its color in the color array otherwise, including changes that
+    /// weren't applied yet. Returns `None` if the key was reset to its original color with
+    /// `direct_reset_key`, or is outside of the matrix.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{Key, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// keyboard.array_set_single(Key::A, 255, 0, 0);
+    /// assert_eq!(keyboard.get_key(Key::A), Some((255, 0, 0)));
+    /// keyboard.direct_set_key(Key::A, 0, 0, 255);
+    /// assert_eq!(keyboard.get_key(Key::A), Some((0, 0, 255)));
+    /// ```
+    pub fn get_key<K: IntoMatrixRowColumn>(&self, key: K) -> Option<(u8, u8, u8)> {
+        let row_and_column = key.get_matrix_row_and_column();
+        self.state.lock().unwrap().get(row_and_column)
+    }
+
+    /// The color array, including changes that weren't applied yet. Colors set directly on
+    /// top of it are not included.
+    pub fn current_frame(&self) -> Frame {
+        self.state.lock().unwrap().array
+    }
+
+    /// Were there changes to the color array since it was last applied with `array_update`?
+    /// While auto-updating, every change is applied right away.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{Key, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// keyboard.array_set_single(Key::A, 255, 255, 255);
+    /// assert!(keyboard.has_pending_changes());
+    /// keyboard.array_update();
+    /// assert!(!keyboard.has_pending_changes());
+    /// ```
+    pub fn has_pending_changes(&self) -> bool {
+        self.state.lock().unwrap().pending
     }
 
     /// Apply any updates made by the `array_set_single` and `array_set_full` functions.
-    /// Returns `true` if the colors are updated.
+    /// Returns `true` if the colors are updated. How they are sent depends on the
+    /// [update strategy](Self::set_update_strategy).
     ///
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Modify keyboard array so A will be set to white..
     /// keyboard.array_set_single(Key::A, 255, 255, 255);
     /// // ..and apply the change.
     /// keyboard.array_update();
     /// ```
     pub fn array_update(&mut self) -> bool {
-        unsafe { wooting_rgb_sys::wooting_rgb_array_update_keyboard() }
+        self.state.lock().unwrap().update(&mut self.backend())
     }
 
     /// Set an auto-update trigger after every change with the `array_set_single` and
@@ -652,7 +1209,7 @@ impl RgbKeyboard {
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Make keyboard array changes apply automatically..
     /// keyboard.array_auto_update(true);
     /// // ..and then modify the array so QWERTY are set to white...
@@ -667,7 +1224,8 @@ impl RgbKeyboard {
     /// ]);
     /// ```
     pub fn array_auto_update(&mut self, auto_update: bool) {
-        unsafe { wooting_rgb_sys::wooting_rgb_array_auto_update(auto_update) }
+        let mut state = self.state.lock().unwrap();
+        state.set_auto_update(&mut self.backend(), auto_update);
     }
 
     /// Set a single color in the color array. This will not directly update the keyboard
@@ -678,7 +1236,7 @@ impl RgbKeyboard {
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Modify the keyboard array so QWERTY will be set to white..
     /// keyboard.array_set_single(Key::Q, 255, 255, 255);
     /// keyboard.array_set_single(Key::W, 255, 255, 255);
@@ -696,8 +1254,7 @@ impl RgbKeyboard {
         green: u8,
         blue: u8,
     ) -> bool {
-        let (row, column) = key.get_matrix_row_and_column();
-        unsafe { wooting_rgb_sys::wooting_rgb_array_set_single(row, column, red, green, blue) }
+        self.array_set_single_matrix(key.get_matrix_row_and_column(), red, green, blue)
     }
 
     /// Set a single color in the color array manually using a (row, column) touple instead
@@ -708,7 +1265,7 @@ impl RgbKeyboard {
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Modify the keyboard array so QWERTY will be set to white..
     /// keyboard.array_set_single_matrix((2, 1), 255, 255, 255);
     /// keyboard.array_set_single_matrix((2, 2), 255, 255, 255);
@@ -726,8 +1283,24 @@ impl RgbKeyboard {
         green: u8,
         blue: u8,
     ) -> bool {
-        let (row, column) = row_and_column;
-        unsafe { wooting_rgb_sys::wooting_rgb_array_set_single(row, column, red, green, blue) }
+        self.set_single(row_and_column, (red, green, blue), false)
+    }
+
+    /// Set a single color in the color array like [`array_set_single`](Self::array_set_single),
+    /// bypassing the output stage.
+    pub fn array_set_single_raw<K: IntoMatrixRowColumn>(
+        &mut self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.set_single(key.get_matrix_row_and_column(), (red, green, blue), true)
+    }
+
+    fn set_single(&mut self, row_and_column: (u8, u8), color: (u8, u8, u8), raw: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.set_single(&mut self.backend(), row_and_column, color, raw)
     }
 
     /// Set a complete color array. This will not directly update the keyboard unless the auto
@@ -737,7 +1310,7 @@ impl RgbKeyboard {
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Modify the keyboard array so QWERTY will be set to white..
     /// keyboard.array_set_full(&[
     ///     (Key::Q, (255, 255, 255)),
@@ -751,16 +1324,39 @@ impl RgbKeyboard {
     /// keyboard.array_update();
     /// ```
     pub fn array_set_full<K: IntoMatrixRowColumn>(&mut self, array: &[(K, (u8, u8, u8))]) -> bool {
-        let mut flattened: [u8; COMPONENTS * COLUMNS * ROWS] = [0; COMPONENTS * COLUMNS * ROWS];
-        for (key, (red, green, blue)) in array {
-            let (row, column) = key.get_matrix_row_and_column();
-            let index: usize =
-                (row as usize) * (COLUMNS * COMPONENTS) + (column as usize) * COMPONENTS;
-            flattened[index] = *red;
-            flattened[index + 1] = *green;
-            flattened[index + 2] = *blue;
+        let mut frame = Frame::default();
+        for (key, color) in array {
+            frame.set(key.get_matrix_row_and_column(), *color);
         }
-        unsafe { wooting_rgb_sys::wooting_rgb_array_set_full(flattened.as_ptr()) }
+        self.array_set_frame(&frame)
+    }
+
+    /// Set a complete color array from a [`Frame`]. This will not directly update the keyboard
+    /// unless the auto update flag is set (see `array_auto_update`). Returns `true` if the
+    /// colors have changed.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{Frame, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// // Modify the keyboard array so every key will be set to white..
+    /// keyboard.array_set_frame(&Frame::filled((255, 255, 255)));
+    /// // ..and apply the change.
+    /// keyboard.array_update();
+    /// ```
+    pub fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.set_frame(frame, false)
+    }
+
+    /// Set a complete color array like [`array_set_frame`](Self::array_set_frame), bypassing
+    /// the output stage.
+    pub fn array_set_frame_raw(&mut self, frame: &Frame) -> bool {
+        self.set_frame(frame, true)
+    }
+
+    fn set_frame(&mut self, frame: &Frame, raw: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.set_frame(&mut self.backend(), frame, raw)
     }
 
     /// Restore all colors to those that were originally on the keyboard. Must be called when
@@ -769,7 +1365,7 @@ impl RgbKeyboard {
     /// ```rust,no_run
     /// use wooting_rgb::{Key, RgbKeyboard};
     ///
-    /// let mut keyboard = RgbKeyboard::default();
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
     /// // Set ABC to white..
     /// keyboard.direct_set_key(Key::A, 255, 255, 255);
     /// keyboard.direct_set_key(Key::B, 255, 255, 255);
@@ -778,16 +1374,504 @@ impl RgbKeyboard {
     /// keyboard.reset_all();
     /// ```
     pub fn reset_all(&mut self) -> bool {
-        unsafe { wooting_rgb_sys::wooting_rgb_reset() }
+        self.state.lock().unwrap().reset(&mut self.backend())
+    }
+}
+
+impl KeyboardState {
+    /// The color of a key, see [`RgbKeyboard::get_key`].
+    fn get(&self, row_and_column: (u8, u8)) -> Option<(u8, u8, u8)> {
+        match self.direct.get(&row_and_column) {
+            Some(direct) => direct.map(|direct| direct.color),
+            None => self.array.get(row_and_column),
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            array: self.array,
+            raw: self.raw,
+            applied: self.applied,
+            direct: self.direct.clone(),
+            auto_update: self.auto_update,
+            pending: self.pending,
+        }
+    }
+
+    fn restore(&mut self, backend: &mut impl Backend, snapshot: &Snapshot) -> bool {
+        self.array = snapshot.array;
+        self.raw = snapshot.raw;
+        self.applied = snapshot.applied;
+        self.direct = snapshot.direct.clone();
+        self.auto_update = snapshot.auto_update;
+        self.pending = snapshot.pending;
+        if self.applied.is_none() && !backend.reset() {
+            return false;
+        }
+        self.show(backend)
+    }
+
+    /// Set a key directly on top of the color array, or reset it to its original color.
+    fn set_direct(
+        &mut self,
+        backend: &mut impl Backend,
+        row_and_column: (u8, u8),
+        direct: Option<DirectColor>,
+    ) -> bool {
+        let (row, column) = row_and_column;
+        self.direct.insert(row_and_column, direct);
+        match direct {
+            Some(direct) => backend.direct_set_key(row, column, self.direct_output(direct)),
+            None => backend.direct_reset_key(row, column),
+        }
+    }
+
+    fn set_auto_update(&mut self, backend: &mut impl Backend, auto_update: bool) {
+        self.auto_update = auto_update;
+        backend.array_auto_update(auto_update);
+    }
+
+    fn set_single(
+        &mut self,
+        backend: &mut impl Backend,
+        row_and_column: (u8, u8),
+        color: (u8, u8, u8),
+        raw: bool,
+    ) -> bool {
+        let (row, column) = row_and_column;
+        if let Some(slot) = self
+            .raw
+            .get_mut(row as usize)
+            .and_then(|row| row.get_mut(column as usize))
+        {
+            *slot = raw;
+        }
+        self.set(|array| {
+            array.set(row_and_column, color);
+        });
+        if self.power.is_some() {
+            // Any key can change how much every other key is dimmed.
+            return backend.array_set_full(&self.output());
+        }
+        let color = if raw { color } else { self.output.apply(color) };
+        backend.array_set_single(row, column, color)
+    }
+
+    fn set_frame(&mut self, backend: &mut impl Backend, frame: &Frame, raw: bool) -> bool {
+        self.raw = [[raw; COLUMNS]; ROWS];
+        self.set(|array| *array = *frame);
+        backend.array_set_full(&self.output())
+    }
+
+    /// Forget the colors and restore the original ones, see [`RgbKeyboard::reset_all`].
+    fn reset(&mut self, backend: &mut impl Backend) -> bool {
+        self.array = Frame::default();
+        self.raw = RawMask::default();
+        self.applied = None;
+        self.direct.clear();
+        self.pending = false;
+        backend.reset()
+    }
+
+    /// Change the color array, which is applied right away while auto-updating.
+    fn set(&mut self, change: impl FnOnce(&mut Frame)) {
+        change(&mut self.array);
+        self.pending = !self.auto_update;
+        if self.auto_update {
+            self.applied = Some(self.render(false));
+            self.direct.clear();
+            self.metrics.record(None);
+        }
+    }
+
+    /// The color to send for a color set directly.
+    fn direct_output(&self, direct: DirectColor) -> (u8, u8, u8) {
+        if direct.raw {
+            direct.color
+        } else {
+            self.output.apply(direct.color)
+        }
+    }
+
+    /// Put the color array into the SDK again after the output stage or power budget changed.
+    fn refresh(&mut self, backend: &mut impl Backend) {
+        self.set(|_| {});
+        backend.array_set_full(&self.output());
+    }
+
+    /// The colors to send for the color array, after the output stage and power budget
+    /// without dithering.
+    fn output(&self) -> Frame {
+        let budget = self.power.as_ref();
+        output::render(&self.output, budget, &self.array, &self.raw, None).0
+    }
+
+    /// The colors to send for the color array, dithered if asked for, keeping the power report.
+    fn render(&mut self, dither: bool) -> Frame {
+        let dither = dither.then_some(&mut self.dither);
+        let budget = self.power.as_ref();
+        let (frame, report) = output::render(&self.output, budget, &self.array, &self.raw, dither);
+        self.power_report = report;
+        frame
+    }
+
+    /// Send the color array, only writing the keys that changed if the strategy allows it.
+    fn update(&mut self, backend: &mut impl Backend) -> bool {
+        let frame = self.render(true);
+        if self.output.dithering {
+            // Every update is dithered anew.
+            backend.array_set_full(&frame);
+        }
+        let changed = match (self.strategy, self.applied) {
+            (UpdateStrategy::Partial { max_keys }, Some(applied)) => {
+                // Keys that were set directly show another color than the array, whether
+                // their color in it changed or not.
+                let mut changed: BTreeSet<_> = self.direct.keys().copied().collect();
+                changed.extend(frame.diff(&applied).map(|(position, _)| position));
+                Some(changed).filter(|changed| changed.len() <= max_keys)
+            }
+            _ => None,
+        };
+        self.applied = Some(frame);
+        self.direct.clear();
+        self.pending = false;
+        self.metrics.record(changed.as_ref().map(BTreeSet::len));
+
+        let Some(changed) = changed else {
+            return backend.array_update_keyboard();
+        };
+        changed.into_iter().all(|(row, column)| {
+            backend.direct_set_key(row, column, frame.get((row, column)).unwrap_or_default())
+        })
+    }
+
+    /// Send everything that should be on the keyboard, such as when it lost it all by being
+    /// unplugged. Returns `true` if the colors are shown.
+    fn show(&self, backend: &mut impl Backend) -> bool {
+        let mut shown = true;
+        backend.array_auto_update(false);
+        if let Some(applied) = self.applied {
+            backend.array_set_full(&applied);
+            shown &= backend.array_update_keyboard();
+        }
+        for (&(row, column), &direct) in &self.direct {
+            shown &= match direct {
+                Some(direct) => backend.direct_set_key(row, column, self.direct_output(direct)),
+                None => backend.direct_reset_key(row, column),
+            };
+        }
+        let output = self.output();
+        if Some(output) != self.applied {
+            backend.array_set_full(&output);
+        }
+        backend.array_auto_update(self.auto_update);
+        shown
     }
 }
 
 impl Drop for RgbKeyboard {
     fn drop(&mut self) {
+        // Stop restoring the colors before they are reset.
+        self.reconnect = None;
         // By restricting all rgb functions to get performed on a struct then we can ensure
         // that there is something to be dropped and therefore force a reset.
         let _ = self.reset_all();
         // Also, make sure that the auto update has been reset.
         self.array_auto_update(false);
+        OPEN.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: (u8, u8, u8) = (255, 0, 0);
+    const BLUE: (u8, u8, u8) = (0, 0, 255);
+    const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub(crate) enum Call {
+        Reset,
+        DirectSetKey(u8, u8, (u8, u8, u8)),
+        DirectResetKey(u8, u8),
+        Update,
+        AutoUpdate(bool),
+        SetSingle(u8, u8, (u8, u8, u8)),
+        SetFull(Box<Frame>),
+    }
+
+    /// Records the calls instead of making them, which all succeed. Clones record into the
+    /// same list.
+    #[derive(Clone, Debug, Default)]
+    pub(crate) struct Recorder(Arc<Mutex<Vec<Call>>>);
+
+    impl Recorder {
+        pub(crate) fn take(&self) -> Vec<Call> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+
+        fn push(&mut self, call: Call) {
+            self.0.lock().unwrap().push(call);
+        }
+    }
+
+    impl Backend for Recorder {
+        fn reset(&mut self) -> bool {
+            self.push(Call::Reset);
+            true
+        }
+
+        fn direct_set_key(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool {
+            self.push(Call::DirectSetKey(row, column, color));
+            true
+        }
+
+        fn direct_reset_key(&mut self, row: u8, column: u8) -> bool {
+            self.push(Call::DirectResetKey(row, column));
+            true
+        }
+
+        fn array_update_keyboard(&mut self) -> bool {
+            self.push(Call::Update);
+            true
+        }
+
+        fn array_auto_update(&mut self, auto_update: bool) {
+            self.push(Call::AutoUpdate(auto_update));
+        }
+
+        fn array_set_single(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool {
+            self.push(Call::SetSingle(row, column, color));
+            true
+        }
+
+        fn array_set_full(&mut self, frame: &Frame) -> bool {
+            self.push(Call::SetFull(Box::new(*frame)));
+            true
+        }
+    }
+
+    impl Backend for Box<dyn Backend> {
+        fn reset(&mut self) -> bool {
+            (**self).reset()
+        }
+
+        fn direct_set_key(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool {
+            (**self).direct_set_key(row, column, color)
+        }
+
+        fn direct_reset_key(&mut self, row: u8, column: u8) -> bool {
+            (**self).direct_reset_key(row, column)
+        }
+
+        fn array_update_keyboard(&mut self) -> bool {
+            (**self).array_update_keyboard()
+        }
+
+        fn array_auto_update(&mut self, auto_update: bool) {
+            (**self).array_auto_update(auto_update)
+        }
+
+        fn array_set_single(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool {
+            (**self).array_set_single(row, column, color)
+        }
+
+        fn array_set_full(&mut self, frame: &Frame) -> bool {
+            (**self).array_set_full(frame)
+        }
+    }
+
+    fn direct(color: (u8, u8, u8)) -> Option<DirectColor> {
+        Some(DirectColor { color, raw: false })
+    }
+
+    #[test]
+    fn partial_update() {
+        let mut backend = Recorder::default();
+        let mut state = KeyboardState {
+            strategy: UpdateStrategy::Partial { max_keys: 3 },
+            ..Default::default()
+        };
+        // Nothing was sent yet to compare with.
+        state.set_frame(&mut backend, &Frame::filled(BLUE), false);
+        assert!(state.update(&mut backend));
+        assert_eq!(
+            backend.take(),
+            [Call::SetFull(Box::new(Frame::filled(BLUE))), Call::Update]
+        );
+
+        state.set_single(&mut backend, (1, 2), WHITE, false);
+        // A key set directly shows another color than the array, even though it didn't change.
+        state.set_direct(&mut backend, (4, 5), direct(RED));
+        backend.take();
+        assert!(state.update(&mut backend));
+        assert_eq!(
+            backend.take(),
+            [
+                Call::DirectSetKey(1, 2, WHITE),
+                Call::DirectSetKey(4, 5, BLUE)
+            ]
+        );
+        assert_eq!(state.direct, DirectColors::new());
+        assert_eq!(
+            (state.metrics.full_updates, state.metrics.partial_updates),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn partial_update_falls_back_to_full() {
+        let mut backend = Recorder::default();
+        let mut state = KeyboardState {
+            strategy: UpdateStrategy::Partial { max_keys: 3 },
+            ..Default::default()
+        };
+        state.update(&mut backend);
+
+        let mut frame = Frame::default();
+        for column in 0..4 {
+            frame.set((2, column), RED);
+        }
+        state.set_frame(&mut backend, &frame, false);
+        backend.take();
+        assert!(state.update(&mut backend));
+        assert_eq!(backend.take(), [Call::Update]);
+        assert_eq!(state.applied, Some(frame));
+        assert_eq!(
+            (state.metrics.full_updates, state.metrics.partial_updates),
+            (2, 0)
+        );
+    }
+
+    #[test]
+    fn pending() {
+        let mut backend = Recorder::default();
+        let mut state = KeyboardState::default();
+        assert!(!state.pending);
+        state.set_single(&mut backend, (1, 1), RED, false);
+        assert!(state.pending);
+        // Keys set directly don't change the color array.
+        state.update(&mut backend);
+        state.set_direct(&mut backend, (1, 1), direct(BLUE));
+        assert!(!state.pending);
+        assert_eq!(state.get((1, 1)), Some(BLUE));
+        state.set_direct(&mut backend, (1, 1), None);
+        assert_eq!(state.get((1, 1)), None);
+
+        // Every change is applied right away while auto-updating.
+        state.set_auto_update(&mut backend, true);
+        state.set_frame(&mut backend, &Frame::filled(WHITE), false);
+        assert!(!state.pending);
+        assert_eq!(state.applied, Some(Frame::filled(WHITE)));
+        assert_eq!(state.get((1, 1)), Some(WHITE));
+
+        state.set_auto_update(&mut backend, false);
+        state.set_single(&mut backend, (1, 1), RED, false);
+        assert!(state.pending);
+        state.reset(&mut backend);
+        assert!(!state.pending);
+        assert_eq!(state.get((1, 1)), Some((0, 0, 0)));
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut backend = Recorder::default();
+        let mut state = KeyboardState::default();
+        state.set_frame(&mut backend, &Frame::filled(BLUE), false);
+        state.update(&mut backend);
+        state.set_direct(&mut backend, (3, 3), direct(RED));
+        state.set_single(&mut backend, (1, 1), WHITE, false);
+        let snapshot = state.snapshot();
+
+        state.set_frame(&mut backend, &Frame::filled(RED), false);
+        state.update(&mut backend);
+        state.set_auto_update(&mut backend, true);
+        backend.take();
+
+        assert!(state.restore(&mut backend, &snapshot));
+        assert_eq!(state.snapshot(), snapshot);
+        assert!(state.pending);
+        assert_eq!(state.get((3, 3)), Some(RED));
+        // The applied colors and the direct ones are shown again, and the pending change is put
+        // back into the SDK without being applied.
+        let mut array = Frame::filled(BLUE);
+        array.set((1, 1), WHITE);
+        assert_eq!(
+            backend.take(),
+            [
+                Call::AutoUpdate(false),
+                Call::SetFull(Box::new(Frame::filled(BLUE))),
+                Call::Update,
+                Call::DirectSetKey(3, 3, RED),
+                Call::SetFull(Box::new(array)),
+                Call::AutoUpdate(false),
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_unapplied() {
+        let mut backend = Recorder::default();
+        let mut state = KeyboardState::default();
+        state.set_single(&mut backend, (1, 1), WHITE, false);
+        let snapshot = state.snapshot();
+        state.update(&mut backend);
+        backend.take();
+
+        // Nothing was applied then, so the original colors come back.
+        assert!(state.restore(&mut backend, &snapshot));
+        assert_eq!(state.applied, None);
+        assert_eq!(
+            backend.take(),
+            [
+                Call::Reset,
+                Call::AutoUpdate(false),
+                Call::SetFull(Box::new(snapshot.frame())),
+                Call::AutoUpdate(false),
+            ]
+        );
+    }
+
+    #[test]
+    fn power_budget() {
+        let mut backend = Recorder::default();
+        // Half of what every key lit up white draws.
+        let budget = PowerBudget::new((ROWS * COLUMNS) as f32 * 15.0 / 2.0);
+        let mut state = KeyboardState {
+            power: Some(budget),
+            ..Default::default()
+        };
+        let half = Frame::filled((128, 128, 128));
+
+        state.set_frame(&mut backend, &Frame::filled(WHITE), false);
+        assert!(state.update(&mut backend));
+        assert_eq!(state.applied, Some(half));
+        assert_eq!(
+            state.power_report,
+            Some(PowerReport {
+                estimated_milliamps: budget.max_milliamps * 2.0,
+                milliamps: budget.max_milliamps,
+                scale: 0.5,
+            })
+        );
+
+        // Raw colors bypass the output stage, but not the budget.
+        state.set_frame(&mut backend, &Frame::filled(WHITE), true);
+        assert_eq!(backend.take().last(), Some(&Call::SetFull(Box::new(half))));
+
+        // Any key can change how much the others are dimmed, so the whole array is sent.
+        state.set_single(&mut backend, (0, 0), (0, 0, 0), false);
+        assert!(matches!(backend.take()[..], [Call::SetFull(_)]));
+
+        // A frame within the budget is sent as it is.
+        let mut frame = Frame::default();
+        frame.set((1, 1), WHITE);
+        state.set_frame(&mut backend, &frame, false);
+        state.update(&mut backend);
+        assert_eq!(state.applied, Some(frame));
+        let report = state.power_report.unwrap();
+        assert_eq!((report.estimated_milliamps, report.scale), (15.0, 1.0));
+        assert!(!report.is_limited());
     }
 }
@@ -0,0 +1,336 @@
+//! Control the keyboard over MQTT, so home automation can use it as a status light. Enabled by
+//! the `mqtt` feature.
+//!
+//! An [`MqttBridge`] subscribes to two topics that take JSON payloads, with colors given as
+//! strings understood by [`parse_color`](crate::parse_color) or as `[red, green, blue]`:
+//!
+//! - `set` colors keys on top of what is shown, such as `{"keys": {"Esc": "red"}}`. `fill`
+//!   colors every key first, as in `{"fill": "black", "keys": {"F1": [0, 255, 0]}}`, and
+//!   `{"reset": true}` restores the colors that were originally on the keyboard.
+//! - `effect` runs one of the [built-in effects](crate::effect), such as
+//!   `{"name": "breathing", "color": "blue", "period": 2.0}`. An empty payload stops it.
+//!
+//! The bridge publishes `{"online": true, "connected": true, "model": "Wooting 80HE"}` to the
+//! retained `state` topic whenever the keyboard is plugged in or unplugged, and
+//! `{"online": false, "connected": false}` once the bridge goes away. Payloads that can't be
+//! handled are answered on the `error` topic.
+
+use std::{
+    thread::{self, sleep},
+    time::Duration,
+};
+
+pub use rumqttc::MqttOptions;
+use rumqttc::{Client, Event, LastWill, Packet, QoS};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    control::{Controller, SetFrame, StartEffect},
+    device_info, is_wooting_keyboard_connected, RgbDevice, WootingError,
+};
+
+const MONITOR_INTERVAL: Duration = Duration::from_millis(500);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// The topics an [`MqttBridge`] uses.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MqttTopics {
+    /// Colors keys.
+    pub set: String,
+    /// Starts and stops effects.
+    pub effect: String,
+    /// Where the connection state is published.
+    pub state: String,
+    /// Where payloads that can't be handled are answered.
+    pub error: String,
+}
+
+impl MqttTopics {
+    /// Name every topic after `prefix`, such as `desk/keyboard/set` for `desk/keyboard`.
+    ///
+    /// ```rust
+    /// use wooting_rgb::mqtt::MqttTopics;
+    ///
+    /// let topics = MqttTopics::with_prefix("desk/keyboard");
+    /// assert_eq!(topics.set, "desk/keyboard/set");
+    /// assert_eq!(topics.effect, "desk/keyboard/effect");
+    /// ```
+    pub fn with_prefix(prefix: &str) -> Self {
+        Self {
+            set: format!("{prefix}/set"),
+            effect: format!("{prefix}/effect"),
+            state: format!("{prefix}/state"),
+            error: format!("{prefix}/error"),
+        }
+    }
+}
+
+impl Default for MqttTopics {
+    /// The topics under `wooting/keyboard`.
+    fn default() -> Self {
+        Self::with_prefix("wooting/keyboard")
+    }
+}
+
+#[derive(Deserialize)]
+struct Set {
+    #[serde(default)]
+    reset: bool,
+    #[serde(flatten)]
+    frame: SetFrame,
+}
+
+/// Connects a device to an MQTT broker.
+///
+/// ```rust,no_run
+/// use wooting_rgb::{
+///     mqtt::{MqttBridge, MqttOptions, MqttTopics},
+///     RgbKeyboard,
+/// };
+///
+/// let options = MqttOptions::new("wooting-rgb", "localhost", 1883);
+/// let topics = MqttTopics::with_prefix("desk/keyboard");
+/// let bridge = MqttBridge::new(options, topics, RgbKeyboard::open().unwrap());
+/// bridge.run();
+/// ```
+pub struct MqttBridge {
+    options: MqttOptions,
+    topics: MqttTopics,
+    controller: Controller,
+}
+
+impl MqttBridge {
+    /// Prepare to connect with `options`, which are given a last will that marks the bridge
+    /// offline.
+    pub fn new<D: RgbDevice + Send + 'static>(
+        mut options: MqttOptions,
+        topics: MqttTopics,
+        device: D,
+    ) -> Self {
+        let offline = json!({ "online": false, "connected": false }).to_string();
+        options.set_last_will(LastWill::new(
+            &topics.state,
+            offline,
+            QoS::AtLeastOnce,
+            true,
+        ));
+        Self {
+            options,
+            topics,
+            controller: Controller::new(device),
+        }
+    }
+
+    /// Handle messages forever, reconnecting whenever the connection to the broker is lost.
+    pub fn run(mut self) {
+        let (client, mut connection) = Client::new(self.options.clone(), 16);
+        {
+            let client = client.clone();
+            let topic = self.topics.state.clone();
+            thread::spawn(move || monitor(&client, &topic));
+        }
+
+        for event in connection.iter() {
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    let _ = client.subscribe(&self.topics.set, QoS::AtLeastOnce);
+                    let _ = client.subscribe(&self.topics.effect, QoS::AtLeastOnce);
+                    publish_state(&client, &self.topics.state);
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if let Err(error) = self.handle(&publish.topic, &publish.payload) {
+                        let payload = json!({ "topic": publish.topic, "error": error });
+                        let _ = client.try_publish(
+                            &self.topics.error,
+                            QoS::AtMostOnce,
+                            false,
+                            payload.to_string(),
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => sleep(RECONNECT_DELAY),
+            }
+        }
+    }
+
+    fn handle(&mut self, topic: &str, payload: &[u8]) -> Result<(), String> {
+        let controller = &mut self.controller;
+        let updated = if topic == self.topics.set {
+            let set: Set = serde_json::from_slice(payload).map_err(|error| error.to_string())?;
+            if set.reset {
+                controller.reset()
+            } else {
+                let frame = set
+                    .frame
+                    .apply(controller.frame())
+                    .map_err(|error| error.to_string())?;
+                controller.set_frame(&frame)
+            }
+        } else if topic == self.topics.effect {
+            if payload.trim_ascii().is_empty() {
+                controller.stop_effect();
+            } else {
+                let request: StartEffect =
+                    serde_json::from_slice(payload).map_err(|error| error.to_string())?;
+                let effect = request.effect().map_err(|error| error.to_string())?;
+                controller.start_effect(effect);
+            }
+            true
+        } else {
+            true
+        };
+
+        if updated {
+            Ok(())
+        } else {
+            Err(WootingError::Disconnected.to_string())
+        }
+    }
+}
+
+/// Publish the connection state whenever the keyboard is plugged in or unplugged, until the
+/// bridge stops.
+fn monitor(client: &Client, topic: &str) {
+    let mut connected = is_wooting_keyboard_connected();
+    loop {
+        sleep(MONITOR_INTERVAL);
+        if is_wooting_keyboard_connected() == connected {
+            continue;
+        }
+        connected = !connected;
+        if !publish_state(client, topic) {
+            return;
+        }
+    }
+}
+
+/// Publish the connection state. Returns `false` if the bridge has stopped.
+fn publish_state(client: &Client, topic: &str) -> bool {
+    let state = match device_info() {
+        Ok(info) => json!({ "online": true, "connected": true, "model": info.model }),
+        Err(_) => json!({ "online": true, "connected": false }),
+    };
+    client
+        .try_publish(topic, QoS::AtLeastOnce, true, state.to_string())
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        process::{Child, Command, Stdio},
+        sync::{
+            mpsc::{self, Receiver},
+            Arc, Mutex,
+        },
+        time::Instant,
+    };
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{Key, VirtualKeyboard};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// A broker that is stopped when dropped.
+    struct Broker(Child);
+
+    impl Drop for Broker {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// Start `mosquitto` on a free port, returning it and the port.
+    fn broker() -> (Broker, u16) {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let child = Command::new("mosquitto")
+            .args(["-p", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Unable to start mosquitto");
+        let broker = Broker(child);
+        let start = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(start.elapsed() < TIMEOUT, "mosquitto didn't start");
+            sleep(Duration::from_millis(50));
+        }
+        (broker, port)
+    }
+
+    /// Connect a client that passes on what is published to `topics`.
+    fn subscribe(port: u16, topics: &[&str]) -> (Client, Receiver<(String, Value)>) {
+        let options = MqttOptions::new("wooting-rgb-test", "127.0.0.1", port);
+        let (client, mut connection) = Client::new(options, 16);
+        for topic in topics {
+            client.subscribe(*topic, QoS::AtLeastOnce).unwrap();
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for event in connection.iter() {
+                if let Ok(Event::Incoming(Packet::Publish(publish))) = event {
+                    let payload = serde_json::from_slice(&publish.payload).unwrap();
+                    if sender.send((publish.topic, payload)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        (client, receiver)
+    }
+
+    #[test]
+    #[ignore = "needs mosquitto"]
+    fn bridge() {
+        let (_broker, port) = broker();
+        let topics = MqttTopics::with_prefix("test/keyboard");
+        let (client, messages) = subscribe(port, &[&topics.state, &topics.error]);
+
+        let device = Arc::new(Mutex::new(VirtualKeyboard::default()));
+        let options = MqttOptions::new("wooting-rgb-bridge", "127.0.0.1", port);
+        let bridge = MqttBridge::new(options, topics.clone(), device.clone());
+        thread::spawn(move || bridge.run());
+
+        // The state is published once the bridge has subscribed.
+        let (topic, state) = messages.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(topic, topics.state);
+        assert_eq!(state["online"], true);
+        assert_eq!(state["connected"], is_wooting_keyboard_connected());
+
+        let publish = |topic: &str, payload: &str| {
+            client
+                .publish(topic, QoS::AtLeastOnce, false, payload)
+                .unwrap();
+        };
+        publish(&topics.set, r#"{"fill": "blue", "keys": {"Esc": "red"}}"#);
+        let start = Instant::now();
+        while device.lock().unwrap().frame().get(Key::Escape) != Some((255, 0, 0)) {
+            assert!(start.elapsed() < TIMEOUT, "The colors weren't set");
+            sleep(Duration::from_millis(10));
+        }
+        let frame = device.lock().unwrap().frame();
+        assert_eq!(frame.get(Key::A), Some((0, 0, 255)));
+
+        // Payloads that can't be handled are answered on the error topic.
+        for (topic, payload) in [
+            (&topics.set, r#"{"keys": {"Nope": "red"}}"#),
+            (&topics.effect, r#"{"name": "rainbow", "period": -1}"#),
+            (&topics.effect, r#"{"name": "disco"}"#),
+        ] {
+            publish(topic, payload);
+            let (error_topic, error) = messages.recv_timeout(TIMEOUT).unwrap();
+            assert_eq!(error_topic, topics.error);
+            assert_eq!(&error["topic"], topic.as_str());
+        }
+    }
+}
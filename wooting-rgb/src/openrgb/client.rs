@@ -0,0 +1,463 @@
+use std::{
+    io::{self, BufReader},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use super::{
+    Decoder, Encoder, Packet, NO_LED, PROTOCOL_VERSION, REQUEST_CONTROLLER_COUNT,
+    REQUEST_CONTROLLER_DATA, REQUEST_PROTOCOL_VERSION, RGBCONTROLLER_SETCUSTOMMODE,
+    RGBCONTROLLER_UPDATELEDS, SET_CLIENT_NAME, ZONE_TYPE_MATRIX, ZONE_TYPE_SINGLE,
+};
+use crate::{Frame, Key, RgbDevice, VirtualKeyboard, LAYOUT_HEIGHT, LAYOUT_WIDTH};
+
+/// Sends colors to every device of an OpenRGB server, so the same code that drives the keyboard
+/// can light mice, strips and fans.
+///
+/// The colors are handled the way the keyboard handles them. Whenever the shown colors change,
+/// every LED of every device is set to the color of the key it is named after or else of the
+/// key closest to where the LED sits within its zone, as if the zone were stretched over the
+/// keyboard. A rainbow moving across the keyboard moves along a strip, and an effect that
+/// colors the whole keyboard colors everything.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use wooting_rgb::{
+///     effect::{self, Rainbow},
+///     openrgb::{OpenRgbClient, DEFAULT_PORT},
+/// };
+///
+/// let mut client = OpenRgbClient::connect(("127.0.0.1", DEFAULT_PORT), "Rainbow").unwrap();
+/// println!("Driving {:?}", client.controllers());
+/// effect::run(&mut Rainbow::default(), &mut client, 30, |elapsed| {
+///     elapsed < Duration::from_secs(10)
+/// });
+/// ```
+pub struct OpenRgbClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    controllers: Vec<Controller>,
+    keyboard: VirtualKeyboard,
+    /// Do changes to the color array show right away?
+    auto_update: bool,
+    /// The colors last sent, if any.
+    sent: Option<Frame>,
+}
+
+struct Controller {
+    name: String,
+    /// The key whose color each LED of the controller shows.
+    leds: Vec<Key>,
+}
+
+impl OpenRgbClient {
+    /// Connect to an OpenRGB server, introducing the client as `name`, and switch every device
+    /// to its direct mode.
+    ///
+    /// ```rust
+    /// use std::thread;
+    ///
+    /// use wooting_rgb::{
+    ///     openrgb::{OpenRgbClient, OpenRgbServer},
+    ///     Frame, RgbDevice, VirtualKeyboard,
+    /// };
+    ///
+    /// // Serve a virtual keyboard to connect to.
+    /// let server = OpenRgbServer::bind("127.0.0.1:0", VirtualKeyboard::default()).unwrap();
+    /// let address = server.local_addr().unwrap();
+    /// thread::spawn(move || server.run());
+    ///
+    /// let mut client = OpenRgbClient::connect(address, "Example").unwrap();
+    /// assert_eq!(client.controllers().len(), 1);
+    /// client.array_set_frame(&Frame::filled((255, 0, 0)));
+    /// assert!(client.array_update());
+    /// ```
+    pub fn connect<A: ToSocketAddrs>(address: A, name: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut client = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            controllers: Vec::new(),
+            keyboard: VirtualKeyboard::default(),
+            auto_update: false,
+            sent: None,
+        };
+
+        let reply = client.request(0, REQUEST_PROTOCOL_VERSION, PROTOCOL_VERSION.to_le_bytes())?;
+        let version = Decoder::new(&reply).u32()?.min(PROTOCOL_VERSION);
+        let mut client_name = Encoder::default();
+        client_name.bytes.extend_from_slice(name.as_bytes());
+        client_name.bytes.push(0);
+        client.send(0, SET_CLIENT_NAME, client_name.bytes)?;
+
+        let reply = client.request(0, REQUEST_CONTROLLER_COUNT, [])?;
+        let count = Decoder::new(&reply).u32()?;
+        for index in 0..count {
+            let request = if version >= 1 {
+                version.to_le_bytes().to_vec()
+            } else {
+                Vec::new()
+            };
+            let reply = client.request(index, REQUEST_CONTROLLER_DATA, request)?;
+            let controller = Controller::parse(&reply, version)?;
+            client.controllers.push(controller);
+            client.send(index, RGBCONTROLLER_SETCUSTOMMODE, [])?;
+        }
+        Ok(client)
+    }
+
+    /// The names of the devices of the server, in the order the server lists them.
+    pub fn controllers(&self) -> Vec<&str> {
+        self.controllers
+            .iter()
+            .map(|controller| controller.name.as_str())
+            .collect()
+    }
+
+    fn send(&mut self, device: u32, id: u32, data: impl Into<Vec<u8>>) -> io::Result<()> {
+        let packet = Packet {
+            device,
+            id,
+            data: data.into(),
+        };
+        packet.write_to(&mut self.writer)
+    }
+
+    /// Send a request and wait for the reply to it, skipping any notifications in between.
+    fn request(&mut self, device: u32, id: u32, data: impl Into<Vec<u8>>) -> io::Result<Vec<u8>> {
+        self.send(device, id, data)?;
+        loop {
+            let reply = Packet::read_from(&mut self.reader)?;
+            if reply.id == id {
+                return Ok(reply.data);
+            }
+        }
+    }
+
+    /// Send the shown colors after a change to the color array, which only shows right away
+    /// while auto-updating.
+    fn sync_array(&mut self) -> bool {
+        !self.auto_update || self.sync()
+    }
+
+    /// Send the shown colors to every device if they changed since they were last sent.
+    fn sync(&mut self) -> bool {
+        let frame = self.keyboard.frame();
+        if self.sent == Some(frame) {
+            return true;
+        }
+        for index in 0..self.controllers.len() {
+            let leds = &self.controllers[index].leds;
+            let mut data = Encoder::default();
+            data.u32(4 + 2 + leds.len() as u32 * 4)
+                .u16(leds.len() as u16);
+            for &key in leds {
+                data.color(frame.get(key).unwrap_or_default());
+            }
+            if self
+                .send(index as u32, RGBCONTROLLER_UPDATELEDS, data.bytes)
+                .is_err()
+            {
+                return false;
+            }
+        }
+        self.sent = Some(frame);
+        true
+    }
+}
+
+impl Controller {
+    /// Read the parts of a controller description that are needed to light it.
+    fn parse(data: &[u8], version: u32) -> io::Result<Self> {
+        let mut data = Decoder::new(data);
+        let _size = data.u32()?;
+        let _device_type = data.u32()?;
+        let name = data.string()?;
+        // The vendor, if sent, description, version, serial and location.
+        for _ in 0..if version >= 1 { 5 } else { 4 } {
+            data.string()?;
+        }
+
+        let modes = data.u16()?;
+        let _active_mode = data.u32()?;
+        for _ in 0..modes {
+            data.string()?;
+            // The value, flags, speed and color limits, speed, direction and color mode.
+            for _ in 0..9 {
+                data.u32()?;
+            }
+            let colors = data.u16()?;
+            for _ in 0..colors {
+                data.color()?;
+            }
+        }
+
+        let mut leds = Vec::new();
+        for _ in 0..data.u16()? {
+            data.string()?;
+            let zone_type = data.u32()? as i32;
+            let (_min, _max, count) = (data.u32()?, data.u32()?, data.u32()? as usize);
+            // Every LED is named further on, in at least a length and a value, which bounds how
+            // many a description of this size can hold.
+            if leds.len().saturating_add(count) > data.remaining() / 6 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Zone has more LEDs than the controller describes",
+                ));
+            }
+            let mut positions = vec![(0.5, 0.5); count];
+            if data.u16()? > 0 {
+                let height = data.u32()? as usize;
+                let width = data.u32()? as usize;
+                for row in 0..height {
+                    for column in 0..width {
+                        let led = data.u32()?;
+                        let position = (
+                            (column as f32 + 0.5) / width as f32,
+                            (row as f32 + 0.5) / height as f32,
+                        );
+                        if let Some(slot) =
+                            positions.get_mut(led as usize).filter(|_| led != NO_LED)
+                        {
+                            *slot = position;
+                        }
+                    }
+                }
+            } else if zone_type != ZONE_TYPE_SINGLE && zone_type != ZONE_TYPE_MATRIX {
+                // Spread the LEDs of a strip across the middle of the keyboard.
+                for (index, position) in positions.iter_mut().enumerate() {
+                    position.0 = (index as f32 + 0.5) / count as f32;
+                }
+            }
+            leds.extend(positions.into_iter().map(closest_key));
+        }
+
+        // The LEDs of keyboards are named after their keys, such as `Key: Escape`.
+        let _count = data.u16()?;
+        for led in leds.iter_mut() {
+            let name = data.string()?;
+            let _value = data.u32()?;
+            if let Ok(key) = name.strip_prefix("Key: ").unwrap_or(&name).parse() {
+                *led = key;
+            }
+        }
+
+        Ok(Self { name, leds })
+    }
+}
+
+/// The key closest to a position on the keyboard, given as fractions of its width and height.
+fn closest_key((x, y): (f32, f32)) -> Key {
+    let (x, y) = (x * LAYOUT_WIDTH, y * LAYOUT_HEIGHT);
+    let distance = |key: &Key| {
+        let (left, top, width, height) = key.physical_rect();
+        let (dx, dy) = (left + width / 2.0 - x, top + height / 2.0 - y);
+        dx * dx + dy * dy
+    };
+    Key::ALL
+        .into_iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap()
+}
+
+impl RgbDevice for OpenRgbClient {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.keyboard
+            .direct_set_key_matrix(row_and_column, red, green, blue)
+            && self.sync()
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        self.keyboard.direct_reset_key_matrix(row_and_column) && self.sync()
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.keyboard
+            .array_set_single_matrix(row_and_column, red, green, blue)
+            && self.sync_array()
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.keyboard.array_set_frame(frame) && self.sync_array()
+    }
+
+    fn array_update(&mut self) -> bool {
+        self.keyboard.array_update() && self.sync()
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        self.auto_update = auto_update;
+        self.keyboard.array_auto_update(auto_update)
+    }
+
+    fn reset_all(&mut self) -> bool {
+        self.keyboard.reset_all() && self.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::mpsc::{self, Receiver},
+        thread,
+    };
+
+    use super::*;
+    use crate::IntoMatrixRowColumn;
+
+    const DEVICE_TYPE_LEDSTRIP: u32 = 4;
+    const ZONE_TYPE_LINEAR: u32 = 1;
+
+    /// A controller with a linear strip of three LEDs and a matrix zone of two, one of which is
+    /// named after a key.
+    fn description() -> Vec<u8> {
+        let mut data = Encoder::default();
+        data.u32(0).u32(DEVICE_TYPE_LEDSTRIP).string("Desk");
+        for string in ["Vendor", "Description", "1.0", "Serial", "Location"] {
+            data.string(string);
+        }
+        // No modes.
+        data.u16(0).u32(0);
+
+        data.u16(2);
+        data.string("Strip")
+            .u32(ZONE_TYPE_LINEAR)
+            .u32(3)
+            .u32(3)
+            .u32(3)
+            .u16(0);
+        data.string("Panel")
+            .u32(ZONE_TYPE_MATRIX as u32)
+            .u32(2)
+            .u32(2)
+            .u32(2);
+        // A matrix of one row and two columns.
+        data.u16(4 * 4).u32(1).u32(2).u32(0).u32(1);
+
+        data.u16(5);
+        for name in ["LED 1", "LED 2", "LED 3", "Key: Escape", "Logo"] {
+            data.string(name).u32(0);
+        }
+        data.bytes
+    }
+
+    /// Serve the controller to one client, passing on the data of every UpdateLEDs packet.
+    fn serve() -> (u16, Receiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = stream.try_clone().unwrap();
+            while let Ok(packet) = Packet::read_from(&mut reader) {
+                let reply = match packet.id {
+                    REQUEST_PROTOCOL_VERSION => PROTOCOL_VERSION.to_le_bytes().to_vec(),
+                    REQUEST_CONTROLLER_COUNT => 1u32.to_le_bytes().to_vec(),
+                    REQUEST_CONTROLLER_DATA => {
+                        assert_eq!(packet.device, 0);
+                        description()
+                    }
+                    RGBCONTROLLER_UPDATELEDS => {
+                        sender.send(packet.data).unwrap();
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let reply = Packet {
+                    data: reply,
+                    ..packet
+                };
+                reply.write_to(&mut stream).unwrap();
+            }
+        });
+        (port, receiver)
+    }
+
+    #[test]
+    fn update_leds() {
+        let (port, updates) = serve();
+        let mut client = OpenRgbClient::connect(("127.0.0.1", port), "Test").unwrap();
+        assert_eq!(client.controllers(), ["Desk"]);
+
+        // Give every key a color of its own, to tell which key every LED shows.
+        let mut frame = Frame::default();
+        for (index, key) in Key::ALL.into_iter().enumerate() {
+            frame.set(key, (index as u8, 1, 2));
+        }
+        // Nothing is sent until the color array is applied.
+        assert!(client.array_set_frame(&frame));
+        assert!(client.array_update());
+
+        // The strip is spread across the middle of the keyboard, the named LED shows its key
+        // and the other one the key closest to where it sits.
+        let keys = [
+            Key::W,
+            Key::I,
+            Key::Backslash,
+            Key::Escape,
+            Key::RightBracket,
+        ];
+        let mut expected = Encoder::default();
+        expected.u32(4 + 2 + 5 * 4).u16(5);
+        for key in keys {
+            expected.color(frame.get(key).unwrap());
+        }
+        assert_eq!(updates.recv().unwrap(), expected.bytes);
+
+        // Nothing is sent when the colors don't change.
+        assert!(client.array_update());
+        assert!(client.array_set_single_matrix(
+            Key::Escape.get_matrix_row_and_column(),
+            255,
+            255,
+            255
+        ));
+        assert!(client.array_update());
+        let update = updates.recv().unwrap();
+        assert_eq!(&update[6 + 3 * 4..6 + 4 * 4], [255, 255, 255, 0]);
+
+        // While auto-updating, changes to the color array are sent right away.
+        client.array_auto_update(true);
+        assert!(client.array_set_single_matrix(Key::Escape.get_matrix_row_and_column(), 0, 0, 0));
+        let update = updates.recv().unwrap();
+        assert_eq!(&update[6 + 3 * 4..6 + 4 * 4], [0, 0, 0, 0]);
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[test]
+    fn closest_keys() {
+        assert_eq!(closest_key((0.0, 0.0)), Key::Escape);
+        assert_eq!(closest_key((1.0, 1.0)), Key::RightArrow);
+    }
+
+    #[test]
+    fn too_many_leds() {
+        // Claim the strip has far more LEDs than fit in the description, after its name, type
+        // and minimum and maximum number of LEDs.
+        let mut data = description();
+        let name = data
+            .windows(5)
+            .position(|window| window == b"Strip")
+            .unwrap();
+        let count = name + 6 + 3 * 4;
+        data[count..count + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = Controller::parse(&data, PROTOCOL_VERSION).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        assert!(Controller::parse(&description(), PROTOCOL_VERSION).is_ok());
+    }
+}
@@ -0,0 +1,206 @@
+//! Speak the [OpenRGB](https://openrgb.org) SDK network protocol, both ways: [`OpenRgbServer`]
+//! lets OpenRGB clients control the keyboard alongside the rest of a desk, and [`OpenRgbClient`]
+//! lets effects written for the keyboard light the devices of an OpenRGB server.
+//!
+//! The keyboard is described to clients as a single controller with one matrix zone. Every
+//! [`Key`](crate::Key) is one LED, named after how the key is displayed.
+
+use std::io::{self, Read, Write};
+
+pub use self::{client::OpenRgbClient, server::OpenRgbServer};
+
+mod client;
+mod server;
+
+/// The port OpenRGB servers listen on by default.
+pub const DEFAULT_PORT: u16 = 6742;
+
+/// The newest protocol version spoken. Clients asking for a newer one are answered with this.
+const PROTOCOL_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"ORGB";
+const HEADER_SIZE: usize = 16;
+/// The largest packet data accepted. Even a big controller description is far smaller, so
+/// anything larger is taken to be garbage rather than allocated.
+const MAX_DATA_SIZE: usize = 1 << 20;
+
+const REQUEST_CONTROLLER_COUNT: u32 = 0;
+const REQUEST_CONTROLLER_DATA: u32 = 1;
+const REQUEST_PROTOCOL_VERSION: u32 = 40;
+const SET_CLIENT_NAME: u32 = 50;
+const RGBCONTROLLER_UPDATELEDS: u32 = 1050;
+const RGBCONTROLLER_UPDATEZONELEDS: u32 = 1051;
+const RGBCONTROLLER_UPDATESINGLELED: u32 = 1052;
+const RGBCONTROLLER_SETCUSTOMMODE: u32 = 1100;
+
+const DEVICE_TYPE_KEYBOARD: i32 = 5;
+const ZONE_TYPE_SINGLE: i32 = 0;
+const ZONE_TYPE_MATRIX: i32 = 2;
+const MODE_FLAG_HAS_PER_LED_COLOR: u32 = 1 << 5;
+const MODE_COLORS_PER_LED: u32 = 1;
+/// Marks a position in a zone's matrix map that has no LED.
+const NO_LED: u32 = u32::MAX;
+
+/// One message of the protocol: which controller it is about, what it is and its data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Packet {
+    device: u32,
+    id: u32,
+    data: Vec<u8>,
+}
+
+impl Packet {
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not an OpenRGB packet",
+            ));
+        }
+        let field = |index: usize| {
+            let start = 4 + index * 4;
+            u32::from_le_bytes(header[start..start + 4].try_into().unwrap())
+        };
+        let size = field(2) as usize;
+        if size > MAX_DATA_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "OpenRGB packet is too large",
+            ));
+        }
+        let mut data = vec![0; size];
+        reader.read_exact(&mut data)?;
+        Ok(Self {
+            device: field(0),
+            id: field(1),
+            data,
+        })
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.device.to_le_bytes());
+        bytes.extend_from_slice(&self.id.to_le_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        writer.write_all(&bytes)
+    }
+}
+
+/// Builds the data of a packet, in the little-endian encoding the protocol uses.
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn i32(&mut self, value: i32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// A string is its length including the terminating null, followed by the null-terminated
+    /// bytes.
+    fn string(&mut self, value: &str) -> &mut Self {
+        self.u16(value.len() as u16 + 1);
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.bytes.push(0);
+        self
+    }
+
+    /// Colors are sent as red, green, blue and an unused byte.
+    fn color(&mut self, (red, green, blue): (u8, u8, u8)) -> &mut Self {
+        self.bytes.extend_from_slice(&[red, green, blue, 0]);
+        self
+    }
+}
+
+/// Reads the data of a packet, failing with [`io::ErrorKind::UnexpectedEof`] if it is too short.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        if self.bytes.len() < N {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (taken, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(taken.try_into().unwrap())
+    }
+
+    /// How many bytes are left to read.
+    fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn color(&mut self) -> io::Result<(u8, u8, u8)> {
+        let [red, green, blue, _] = self.take()?;
+        Ok((red, green, blue))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.u16()? as usize;
+        if self.bytes.len() < length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (string, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        let string = string.strip_suffix(&[0]).unwrap_or(string);
+        Ok(String::from_utf8_lossy(string).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_round_trip() {
+        let packet = Packet {
+            device: 3,
+            id: RGBCONTROLLER_UPDATELEDS,
+            data: vec![1, 2, 3],
+        };
+        let mut bytes = Vec::new();
+        packet.write_to(&mut bytes).unwrap();
+        assert_eq!(Packet::read_from(&mut &bytes[..]).unwrap(), packet);
+    }
+
+    #[test]
+    fn oversized_packet() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&RGBCONTROLLER_UPDATELEDS.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        let error = Packet::read_from(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
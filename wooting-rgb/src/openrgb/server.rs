@@ -0,0 +1,406 @@
+use std::{
+    io::{self, BufReader},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use super::{
+    Decoder, Encoder, Packet, DEVICE_TYPE_KEYBOARD, MODE_COLORS_PER_LED,
+    MODE_FLAG_HAS_PER_LED_COLOR, NO_LED, PROTOCOL_VERSION, REQUEST_CONTROLLER_COUNT,
+    REQUEST_CONTROLLER_DATA, REQUEST_PROTOCOL_VERSION, RGBCONTROLLER_SETCUSTOMMODE,
+    RGBCONTROLLER_UPDATELEDS, RGBCONTROLLER_UPDATESINGLELED, RGBCONTROLLER_UPDATEZONELEDS,
+    ZONE_TYPE_MATRIX,
+};
+use crate::{device_info, IntoMatrixRowColumn, Key, RgbDevice, COLUMNS, ROWS};
+
+/// Serves a device to OpenRGB clients, forwarding the colors they set to
+/// [`array_set_full`](RgbDevice::array_set_full).
+///
+/// ```rust,no_run
+/// use wooting_rgb::{openrgb::{OpenRgbServer, DEFAULT_PORT}, RgbKeyboard};
+///
+/// let server = OpenRgbServer::bind(("127.0.0.1", DEFAULT_PORT), RgbKeyboard::open().unwrap()).unwrap();
+/// server.run().unwrap();
+/// ```
+pub struct OpenRgbServer<D> {
+    listener: TcpListener,
+    controller: Arc<Mutex<Controller<D>>>,
+}
+
+struct Controller<D> {
+    device: D,
+    name: String,
+    /// The colors of the LEDs, in [`Key::ALL`] order.
+    colors: Vec<(u8, u8, u8)>,
+}
+
+impl<D: RgbDevice + Send + 'static> OpenRgbServer<D> {
+    /// Listen for clients on `address`. The controller is named after the connected model.
+    pub fn bind<A: ToSocketAddrs>(address: A, device: D) -> io::Result<Self> {
+        let name = device_info()
+            .map(|info| info.model)
+            .unwrap_or_else(|_| "Wooting keyboard".into());
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            controller: Arc::new(Mutex::new(Controller {
+                device,
+                name,
+                colors: vec![(0, 0, 0); Key::ALL.len()],
+            })),
+        })
+    }
+
+    /// The address the server is listening on, useful when binding to port `0`.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept clients until listening fails, serving each on its own thread.
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let controller = self.controller.clone();
+            thread::spawn(move || serve(stream, &controller));
+        }
+    }
+}
+
+/// Answer the requests of one client until it disconnects or sends something invalid.
+fn serve<D: RgbDevice>(stream: TcpStream, controller: &Mutex<Controller<D>>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut version = 0;
+    loop {
+        let request = Packet::read_from(&mut reader)?;
+        let mut data = Decoder::new(&request.data);
+        let respond = |data: Vec<u8>| Packet {
+            device: request.device,
+            id: request.id,
+            data,
+        };
+        let mut controller = controller.lock().unwrap();
+        let response = match request.id {
+            REQUEST_CONTROLLER_COUNT => Some(respond(Encoder::default().u32(1).bytes.clone())),
+            REQUEST_CONTROLLER_DATA if request.device == 0 => {
+                Some(respond(controller.describe(version)))
+            }
+            REQUEST_PROTOCOL_VERSION => {
+                version = data.u32().unwrap_or(0).min(PROTOCOL_VERSION);
+                Some(respond(PROTOCOL_VERSION.to_le_bytes().to_vec()))
+            }
+            RGBCONTROLLER_UPDATELEDS | RGBCONTROLLER_UPDATEZONELEDS => {
+                let _size = data.u32()?;
+                if request.id == RGBCONTROLLER_UPDATEZONELEDS {
+                    // There is only one zone, holding every LED.
+                    let _zone = data.u32()?;
+                }
+                let count = data.u16()?;
+                for index in 0..count as usize {
+                    let color = data.color()?;
+                    if let Some(led) = controller.colors.get_mut(index) {
+                        *led = color;
+                    }
+                }
+                controller.show();
+                None
+            }
+            RGBCONTROLLER_UPDATESINGLELED => {
+                let index = data.u32()? as usize;
+                let color = data.color()?;
+                if let Some(led) = controller.colors.get_mut(index) {
+                    *led = color;
+                    let (red, green, blue) = color;
+                    controller
+                        .device
+                        .array_set_single(Key::ALL[index], red, green, blue);
+                    controller.device.array_update();
+                }
+                None
+            }
+            RGBCONTROLLER_SETCUSTOMMODE => {
+                controller.show();
+                None
+            }
+            // There is only the direct mode and a single zone with a fixed size, and the name
+            // of the client isn't used, so other requests are ignored.
+            _ => None,
+        };
+        drop(controller);
+        if let Some(response) = response {
+            response.write_to(&mut writer)?;
+        }
+    }
+}
+
+impl<D: RgbDevice> Controller<D> {
+    /// Send the colors of every LED to the device.
+    fn show(&mut self) {
+        let array: Vec<_> = Key::ALL.into_iter().zip(self.colors.clone()).collect();
+        self.device.array_set_full(&array);
+        self.device.array_update();
+    }
+
+    /// Describe the controller in the format of the protocol `version`.
+    fn describe(&self, version: u32) -> Vec<u8> {
+        let mut data = Encoder::default();
+        data.i32(DEVICE_TYPE_KEYBOARD).string(&self.name);
+        if version >= 1 {
+            data.string("Wooting");
+        }
+        data.string("Wooting keyboard")
+            .string(env!("CARGO_PKG_VERSION"))
+            .string("")
+            .string("wooting-rgb");
+
+        // A single direct mode in which every LED has its own color.
+        data.u16(1).i32(0);
+        data.string("Direct")
+            .i32(0)
+            .u32(MODE_FLAG_HAS_PER_LED_COLOR)
+            .u32(0)
+            .u32(0)
+            .u32(0)
+            .u32(0)
+            .u32(0)
+            .u32(0)
+            .u32(MODE_COLORS_PER_LED)
+            .u16(0);
+
+        // A single zone that maps the LEDs onto the key matrix.
+        let leds = Key::ALL.len() as u32;
+        let mut map = vec![NO_LED; ROWS * COLUMNS];
+        for (index, key) in Key::ALL.into_iter().enumerate() {
+            let (row, column) = key.get_matrix_row_and_column();
+            map[row as usize * COLUMNS + column as usize] = index as u32;
+        }
+        data.u16(1)
+            .string("Keyboard")
+            .i32(ZONE_TYPE_MATRIX)
+            .u32(leds)
+            .u32(leds)
+            .u32(leds)
+            .u16((8 + map.len() * 4) as u16)
+            .u32(ROWS as u32)
+            .u32(COLUMNS as u32);
+        for led in map {
+            data.u32(led);
+        }
+
+        data.u16(leds as u16);
+        for (index, key) in Key::ALL.into_iter().enumerate() {
+            data.string(&key.to_string()).u32(index as u32);
+        }
+
+        data.u16(leds as u16);
+        for &color in &self.colors {
+            data.color(color);
+        }
+
+        // The data starts with its own size, including the size itself.
+        let mut bytes = ((data.bytes.len() + 4) as u32).to_le_bytes().to_vec();
+        bytes.append(&mut data.bytes);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Frame, VirtualKeyboard};
+
+    /// A virtual keyboard that notes which of the required methods were called.
+    #[derive(Default)]
+    struct Logged {
+        keyboard: VirtualKeyboard,
+        calls: Vec<&'static str>,
+    }
+
+    impl RgbDevice for Logged {
+        fn direct_set_key_matrix(&mut self, key: (u8, u8), red: u8, green: u8, blue: u8) -> bool {
+            self.calls.push("direct_set_key");
+            self.keyboard.direct_set_key_matrix(key, red, green, blue)
+        }
+
+        fn direct_reset_key_matrix(&mut self, key: (u8, u8)) -> bool {
+            self.calls.push("direct_reset_key");
+            self.keyboard.direct_reset_key_matrix(key)
+        }
+
+        fn array_set_single_matrix(&mut self, key: (u8, u8), red: u8, green: u8, blue: u8) -> bool {
+            self.calls.push("set_single");
+            self.keyboard.array_set_single_matrix(key, red, green, blue)
+        }
+
+        fn array_set_frame(&mut self, frame: &Frame) -> bool {
+            self.calls.push("set_frame");
+            self.keyboard.array_set_frame(frame)
+        }
+
+        fn array_update(&mut self) -> bool {
+            self.calls.push("update");
+            self.keyboard.array_update()
+        }
+
+        fn array_auto_update(&mut self, auto_update: bool) {
+            self.calls.push("auto_update");
+            self.keyboard.array_auto_update(auto_update)
+        }
+
+        fn reset_all(&mut self) -> bool {
+            self.calls.push("reset");
+            self.keyboard.reset_all()
+        }
+    }
+
+    /// Serve a logged keyboard on a free port, returning the port and the keyboard.
+    fn start() -> (u16, Arc<Mutex<Logged>>) {
+        let device = Arc::new(Mutex::new(Logged::default()));
+        let server = OpenRgbServer::bind("127.0.0.1:0", device.clone()).unwrap();
+        let port = server.local_addr().unwrap().port();
+        thread::spawn(move || server.run());
+        (port, device)
+    }
+
+    /// Send a request and, for those that are answered, wait for the answer.
+    fn request(stream: &mut TcpStream, id: u32, data: &Encoder) -> Option<Packet> {
+        let packet = Packet {
+            device: 0,
+            id,
+            data: data.bytes.clone(),
+        };
+        packet.write_to(stream).unwrap();
+        match id {
+            REQUEST_CONTROLLER_COUNT | REQUEST_CONTROLLER_DATA | REQUEST_PROTOCOL_VERSION => {
+                Some(Packet::read_from(stream).unwrap())
+            }
+            // The server handles the requests of a client in order, so once the next answer
+            // arrives, this one has been handled.
+            _ => {
+                request(stream, REQUEST_CONTROLLER_COUNT, &Encoder::default());
+                None
+            }
+        }
+    }
+
+    /// The vendor and the names of the LEDs in a controller description.
+    fn describe(data: &[u8], version: u32) -> io::Result<(Option<String>, Vec<String>)> {
+        let mut data = Decoder::new(data);
+        let _size = data.u32()?;
+        let _type = data.u32()?;
+        let _name = data.string()?;
+        let vendor = if version >= 1 {
+            Some(data.string()?)
+        } else {
+            None
+        };
+        for _ in ["description", "version", "serial", "location"] {
+            data.string()?;
+        }
+
+        let modes = data.u16()?;
+        let _active = data.u32()?;
+        for _ in 0..modes {
+            data.string()?;
+            for _ in 0..9 {
+                data.u32()?;
+            }
+            for _ in 0..data.u16()? {
+                data.color()?;
+            }
+        }
+
+        for _ in 0..data.u16()? {
+            data.string()?;
+            for _ in 0..4 {
+                data.u32()?;
+            }
+            for _ in 0..data.u16()? {
+                data.take::<1>()?;
+            }
+        }
+
+        let mut names = Vec::new();
+        for _ in 0..data.u16()? {
+            names.push(data.string()?);
+            data.u32()?;
+        }
+        Ok((vendor, names))
+    }
+
+    #[test]
+    fn describe_keys() {
+        let (port, _device) = start();
+        let names: Vec<_> = Key::ALL.into_iter().map(|key| key.to_string()).collect();
+
+        // Clients that don't ask for a version speak the first one, without a vendor.
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let description = request(&mut stream, REQUEST_CONTROLLER_DATA, &Encoder::default());
+        let description = describe(&description.unwrap().data, 0).unwrap();
+        assert_eq!(description, (None, names.clone()));
+
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut version = Encoder::default();
+        version.u32(PROTOCOL_VERSION + 1);
+        let answer = request(&mut stream, REQUEST_PROTOCOL_VERSION, &version).unwrap();
+        assert_eq!(answer.data, PROTOCOL_VERSION.to_le_bytes());
+        let mut data = Encoder::default();
+        data.u32(PROTOCOL_VERSION);
+        let description = request(&mut stream, REQUEST_CONTROLLER_DATA, &data).unwrap();
+        let description = describe(&description.data, PROTOCOL_VERSION).unwrap();
+        assert_eq!(description, (Some("Wooting".into()), names));
+    }
+
+    #[test]
+    fn update_leds() {
+        let (port, device) = start();
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+
+        // Give every key a color of its own.
+        let colors = |green| {
+            let mut data = Encoder::default();
+            data.u16(Key::ALL.len() as u16);
+            for index in 0..Key::ALL.len() {
+                data.color((index as u8, green, 2));
+            }
+            data.bytes
+        };
+        let shown = |green| {
+            let mut frame = Frame::default();
+            for (index, key) in Key::ALL.into_iter().enumerate() {
+                frame.set(key, (index as u8, green, 2));
+            }
+            frame
+        };
+
+        let mut data = Encoder::default();
+        data.u32(0).bytes.extend(colors(1));
+        assert!(request(&mut stream, RGBCONTROLLER_UPDATELEDS, &data).is_none());
+        let mut logged = device.lock().unwrap();
+        assert_eq!(logged.keyboard.frame(), shown(1));
+        assert_eq!(logged.calls, ["set_frame", "update"]);
+        logged.calls.clear();
+        drop(logged);
+
+        let mut data = Encoder::default();
+        data.u32(0).u32(0).bytes.extend(colors(3));
+        assert!(request(&mut stream, RGBCONTROLLER_UPDATEZONELEDS, &data).is_none());
+        let mut logged = device.lock().unwrap();
+        assert_eq!(logged.keyboard.frame(), shown(3));
+        assert_eq!(logged.calls, ["set_frame", "update"]);
+        logged.calls.clear();
+        drop(logged);
+
+        let index = Key::ALL.iter().position(|&key| key == Key::Escape).unwrap();
+        let mut data = Encoder::default();
+        data.u32(index as u32).color((255, 255, 255));
+        assert!(request(&mut stream, RGBCONTROLLER_UPDATESINGLELED, &data).is_none());
+        let mut frame = shown(3);
+        frame.set(Key::Escape, (255, 255, 255));
+        let logged = device.lock().unwrap();
+        assert_eq!(logged.keyboard.frame(), frame);
+        assert_eq!(logged.calls, ["set_single", "update"]);
+    }
+}
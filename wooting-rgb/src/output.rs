@@ -0,0 +1,304 @@
+//! Correct colors for the LEDs before they are sent to the keyboard.
+//!
+//! LEDs don't look like screens: the same values look bluer and the dark end has few usable
+//! steps. An [`OutputStage`] set on the keyboard with
+//! [`RgbKeyboard::set_output_stage`](crate::RgbKeyboard::set_output_stage) scales every color
+//! by a global brightness and a gain per channel, applies a gamma curve, and can dither over
+//! time to show the steps in between the 8-bit values. Colors written with the `_raw` methods
+//! bypass it. The [power budget](crate::power) still limits the color array set with them, but
+//! not colors set directly with `direct_set_key` or `direct_set_key_raw`.
+//!
+//! ```rust,no_run
+//! use wooting_rgb::{output::OutputStage, Frame, RgbKeyboard};
+//!
+//! let mut keyboard = RgbKeyboard::open().unwrap();
+//! keyboard.set_output_stage(OutputStage {
+//!     brightness: 0.5,
+//!     gain: (1.0, 0.9, 0.7),
+//!     gamma: 2.2,
+//!     dithering: true,
+//! })
+//! .unwrap();
+//! keyboard.array_set_frame(&Frame::filled((255, 255, 255)));
+//! keyboard.array_update();
+//! ```
+
+use crate::{
+    power::{PowerBudget, PowerReport},
+    Frame, WootingError, COLUMNS, COMPONENTS, ROWS,
+};
+
+/// How colors are corrected before they are sent. The default leaves them untouched. The
+/// keyboard only takes stages that pass [`check`](Self::check).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputStage {
+    /// Scales every color, from `0.0` (off) to `1.0` (unchanged).
+    pub brightness: f32,
+    /// Scales the red, green and blue channels, to balance the white point.
+    pub gain: (f32, f32, f32),
+    /// The exponent of the curve every channel is raised to. Values above `1.0` give more steps
+    /// to the dark end, which LEDs show too brightly otherwise.
+    pub gamma: f32,
+    /// Carry what is lost to rounding over to the next frame, so colors in between two 8-bit
+    /// values are shown by alternating between them. Only applies to the color array, and
+    /// needs frequent updates to look steady.
+    pub dithering: bool,
+}
+
+impl Default for OutputStage {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            gain: (1.0, 1.0, 1.0),
+            gamma: 1.0,
+            dithering: false,
+        }
+    }
+}
+
+impl OutputStage {
+    /// Does this leave every color untouched?
+    pub fn is_identity(&self) -> bool {
+        self.brightness == 1.0 && self.gain == (1.0, 1.0, 1.0) && self.gamma == 1.0
+    }
+
+    /// Make sure the stage can correct colors: every value must be finite, the gamma above zero
+    /// and the brightness and gains not negative. Fails with [`WootingError::InvalidOutputStage`]
+    /// otherwise.
+    ///
+    /// ```rust
+    /// use wooting_rgb::output::OutputStage;
+    ///
+    /// assert!(OutputStage::default().check().is_ok());
+    /// // With a gamma of zero, every channel would be raised to `1.0`, lighting up black keys.
+    /// let stage = OutputStage {
+    ///     gamma: 0.0,
+    ///     ..Default::default()
+    /// };
+    /// assert!(stage.check().is_err());
+    /// ```
+    pub fn check(&self) -> Result<(), WootingError> {
+        let (red_gain, green_gain, blue_gain) = self.gain;
+        let scales = [self.brightness, red_gain, green_gain, blue_gain];
+        let valid = self.gamma.is_finite()
+            && self.gamma > 0.0
+            && scales
+                .iter()
+                .all(|scale| scale.is_finite() && *scale >= 0.0);
+        if valid {
+            Ok(())
+        } else {
+            Err(WootingError::InvalidOutputStage)
+        }
+    }
+
+    /// Correct a color, without rounding it to 8 bits.
+    pub fn correct(&self, color: (u8, u8, u8)) -> (f32, f32, f32) {
+        let (red, green, blue) = color;
+        let (red_gain, green_gain, blue_gain) = self.gain;
+        let channel = |value: u8, gain: f32| {
+            let linear = (value as f32 / 255.0).powf(self.gamma);
+            (linear * gain * self.brightness).clamp(0.0, 1.0) * 255.0
+        };
+        (
+            channel(red, red_gain),
+            channel(green, green_gain),
+            channel(blue, blue_gain),
+        )
+    }
+
+    /// Correct a color and round it to 8 bits.
+    ///
+    /// ```rust
+    /// use wooting_rgb::output::OutputStage;
+    ///
+    /// let stage = OutputStage {
+    ///     brightness: 0.5,
+    ///     gamma: 2.0,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(stage.apply((255, 128, 0)), (128, 32, 0));
+    /// ```
+    pub fn apply(&self, color: (u8, u8, u8)) -> (u8, u8, u8) {
+        let (red, green, blue) = self.correct(color);
+        (red.round() as u8, green.round() as u8, blue.round() as u8)
+    }
+}
+
+/// Which positions in the color array were written raw, bypassing the output stage.
+pub(crate) type RawMask = [[bool; COLUMNS]; ROWS];
+
+/// What temporal dithering lost to rounding at every position, carried over to the next frame.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Dither {
+    error: [[[f32; COMPONENTS]; COLUMNS]; ROWS],
+}
+
+/// Run a color array through the output stage and dim it to the power budget if there is one,
+/// dithering it if the stage asks for it and `dither` is given.
+pub(crate) fn render(
+    stage: &OutputStage,
+    budget: Option<&PowerBudget>,
+    array: &Frame,
+    raw: &RawMask,
+    dither: Option<&mut Dither>,
+) -> (Frame, Option<PowerReport>) {
+    let mut dither = dither.filter(|_| stage.dithering);
+    if stage.is_identity() && dither.is_none() && budget.is_none() {
+        return (*array, None);
+    }
+
+    let mut colors = [[[0.0; COMPONENTS]; COLUMNS]; ROWS];
+    for (row, raw) in raw.iter().enumerate() {
+        for (column, &raw) in raw.iter().enumerate() {
+            let color = array.get((row as u8, column as u8)).unwrap_or_default();
+            let (red, green, blue) = if raw {
+                (color.0 as f32, color.1 as f32, color.2 as f32)
+            } else {
+                stage.correct(color)
+            };
+            colors[row][column] = [red, green, blue];
+        }
+    }
+
+    let report = budget.map(|budget| {
+        let milliamps = colors
+            .iter()
+            .flatten()
+            .map(|&[red, green, blue]| budget.color(red, green, blue))
+            .sum();
+        budget.limit(milliamps)
+    });
+    let scale = report.map_or(1.0, |report| report.scale);
+
+    let mut output = Frame::default();
+    for (row, colors) in colors.iter().enumerate() {
+        for (column, color) in colors.iter().enumerate() {
+            let [red, green, blue] = color.map(|value| value * scale);
+            // Raw colors are left as they are, unless the power budget dims them.
+            let color = if raw[row][column] && scale == 1.0 {
+                (red as u8, green as u8, blue as u8)
+            } else if let Some(dither) = dither.as_deref_mut() {
+                let [red_error, green_error, blue_error] = &mut dither.error[row][column];
+                (
+                    quantize(red, red_error),
+                    quantize(green, green_error),
+                    quantize(blue, blue_error),
+                )
+            } else {
+                (red.round() as u8, green.round() as u8, blue.round() as u8)
+            };
+            output.set((row as u8, column as u8), color);
+        }
+    }
+    (output, report)
+}
+
+/// Round a channel after adding what was lost to rounding before, and keep what is lost now.
+fn quantize(value: f32, error: &mut f32) -> u8 {
+    let wanted = value + *error;
+    let shown = wanted.round().clamp(0.0, 255.0);
+    *error = wanted - shown;
+    shown as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF: OutputStage = OutputStage {
+        brightness: 0.5,
+        gain: (1.0, 1.0, 1.0),
+        gamma: 1.0,
+        dithering: false,
+    };
+
+    #[test]
+    fn check() {
+        assert_eq!(HALF.check(), Ok(()));
+        let invalid = [
+            OutputStage { gamma: 0.0, ..HALF },
+            OutputStage {
+                gamma: -1.0,
+                ..HALF
+            },
+            OutputStage {
+                gamma: f32::INFINITY,
+                ..HALF
+            },
+            OutputStage {
+                brightness: f32::NAN,
+                ..HALF
+            },
+            OutputStage {
+                brightness: -0.5,
+                ..HALF
+            },
+            OutputStage {
+                gain: (1.0, f32::NAN, 1.0),
+                ..HALF
+            },
+            OutputStage {
+                gain: (1.0, 1.0, -1.0),
+                ..HALF
+            },
+        ];
+        for stage in invalid {
+            assert_eq!(
+                stage.check(),
+                Err(WootingError::InvalidOutputStage),
+                "{stage:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn render_identity() {
+        let mut array = Frame::filled((10, 20, 30));
+        array.set((1, 1), (255, 128, 0));
+        let raw = RawMask::default();
+        let (frame, report) = render(&OutputStage::default(), None, &array, &raw, None);
+        assert_eq!(frame, array);
+        assert_eq!(report, None);
+    }
+
+    #[test]
+    fn render_corrects_all_but_raw() {
+        let array = Frame::filled((200, 100, 50));
+        let mut raw = RawMask::default();
+        raw[2][3] = true;
+        let (frame, _) = render(&HALF, None, &array, &raw, None);
+        assert_eq!(frame.get((0, 0)), Some((100, 50, 25)));
+        assert_eq!(frame.get((2, 3)), Some((200, 100, 50)));
+    }
+
+    #[test]
+    fn dithering_carries_the_rounding_error() {
+        // Half of 255 is 127.5, which is shown by alternating between 128 and 127.
+        let stage = OutputStage {
+            dithering: true,
+            ..HALF
+        };
+        let array = Frame::filled((255, 255, 255));
+        let raw = RawMask::default();
+        let mut dither = Dither::default();
+        let shown: Vec<_> = (0..4)
+            .map(|_| render(&stage, None, &array, &raw, Some(&mut dither)).0)
+            .map(|frame| frame.get((0, 0)).unwrap().0)
+            .collect();
+        assert_eq!(shown, [128, 127, 128, 127]);
+
+        // Without a dither to carry the error, every frame is rounded the same way.
+        let (frame, _) = render(&stage, None, &array, &raw, None);
+        assert_eq!(frame.get((0, 0)), Some((128, 128, 128)));
+    }
+
+    #[test]
+    fn quantize() {
+        // A quarter step is shown once every four frames.
+        let mut error = 0.0;
+        let shown: Vec<_> = (0..8).map(|_| super::quantize(10.25, &mut error)).collect();
+        assert_eq!(shown, [10, 11, 10, 10, 10, 11, 10, 10]);
+        assert_eq!(super::quantize(-1.0, &mut 0.0), 0);
+    }
+}
@@ -0,0 +1,400 @@
+//! Keep array updates to a rate the keyboard can sustain.
+//!
+//! A [`FramePacer`] hands out one slot per interval. Frames submitted faster than that are
+//! coalesced, so only the latest one is sent once its slot comes up, and the pacer counts the
+//! frames that were dropped that way as well as the frames that went out late. A
+//! [`PacedDevice`] paces the `array_update` calls made on any [`RgbDevice`], and
+//! [`effect::run_paced`](crate::effect::run_paced) paces an effect.
+//!
+//! ```rust,no_run
+//! use std::{thread::sleep, time::Duration};
+//!
+//! use wooting_rgb::{pacing::{FramePacer, PacedDevice}, RgbDevice, RgbKeyboard};
+//!
+//! let keyboard = RgbKeyboard::open().unwrap();
+//! let mut device = PacedDevice::new(keyboard, FramePacer::new(30));
+//! for column in 0..21 {
+//!     device.array_set_single((1, column), 255, 0, 0);
+//!     // Updates that come too quickly are held back until the next slot.
+//!     device.array_update();
+//!     sleep(Duration::from_millis(5));
+//! }
+//! // Send the last frame, if it was held back.
+//! device.flush();
+//! println!("{:?}", device.stats());
+//! ```
+
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::{Frame, RgbDevice};
+
+/// The frame rate a [`FramePacer`] is limited to by default. This isn't a measured limit of the
+/// keyboards but the refresh rate of most displays, so that effects following what is on screen
+/// can keep up with it. Devices that take longer than an interval to apply a frame show up as
+/// late frames in the [`PacingStats`], and a lower rate such as the 30 the CLI uses suits them.
+pub const DEFAULT_MAX_FPS: u32 = 60;
+
+/// What happened to the frames submitted to a [`FramePacer`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PacingStats {
+    /// Frames that were sent.
+    pub sent: u64,
+    /// Frames that were never sent, because a newer frame replaced them before their slot.
+    pub dropped: u64,
+    /// Frames that were sent more than an interval after they were due.
+    pub late: u64,
+}
+
+/// Limits how often frames are sent. See the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct FramePacer {
+    interval: Duration,
+    /// The earliest time the next frame may be sent.
+    next: Option<Instant>,
+    /// When the frame that is held back is due, if there is one.
+    pending: Option<Instant>,
+    /// The slot that was waited for, which the next frame is due at.
+    slot: Option<Instant>,
+    stats: PacingStats,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FPS)
+    }
+}
+
+impl FramePacer {
+    /// Send at most `max_fps` frames per second.
+    pub fn new(max_fps: u32) -> Self {
+        Self::with_interval(Duration::from_secs(1) / max_fps.max(1))
+    }
+
+    /// Leave at least `interval` between frames, on average.
+    pub fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: None,
+            pending: None,
+            slot: None,
+            stats: PacingStats::default(),
+        }
+    }
+
+    /// The time between two slots.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// What happened to the frames submitted so far.
+    pub fn stats(&self) -> PacingStats {
+        self.stats
+    }
+
+    /// Submit a new frame. Returns `true` if it should be sent right away, or `false` if it is
+    /// held back until its slot, replacing any frame that was held back before.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use wooting_rgb::pacing::FramePacer;
+    ///
+    /// let mut pacer = FramePacer::with_interval(Duration::from_secs(60));
+    /// assert!(pacer.submit());
+    /// // These come too quickly, so only the last one will be sent.
+    /// assert!(!pacer.submit());
+    /// assert!(!pacer.submit());
+    /// assert_eq!(pacer.stats().dropped, 1);
+    /// ```
+    pub fn submit(&mut self) -> bool {
+        if self.pending.take().is_some() {
+            self.stats.dropped += 1;
+        }
+        let now = Instant::now();
+        let due = match self.slot.take() {
+            Some(slot) => slot,
+            None => self.next.map_or(now, |next| next.max(now)),
+        };
+        if due > now {
+            self.pending = Some(due);
+            return false;
+        }
+        self.send(due, now);
+        true
+    }
+
+    /// When the frame that is held back is due, if there is one.
+    pub fn due(&self) -> Option<Instant> {
+        self.pending
+    }
+
+    /// Returns `true` if the frame that was held back is due, in which case it should be sent
+    /// now.
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+        match self.pending {
+            Some(due) if due <= now => {
+                self.pending = None;
+                self.send(due, now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Wait until the frame that was held back is due and return `true`, or return `false`
+    /// right away if there is none.
+    pub fn flush(&mut self) -> bool {
+        let Some(due) = self.pending else {
+            return false;
+        };
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            sleep(wait);
+        }
+        self.poll()
+    }
+
+    /// Wait for the next slot, after which a submitted frame is sent right away. This suits
+    /// loops that render a frame whenever they may send one. The next frame is due at the
+    /// slot, so it is late if rendering it takes longer than an interval.
+    pub fn wait(&mut self) {
+        let Some(next) = self.next else {
+            return;
+        };
+        if let Some(wait) = next.checked_duration_since(Instant::now()) {
+            sleep(wait);
+        }
+        self.slot = Some(next);
+    }
+
+    /// Drop the frame that was held back, if there is one.
+    pub fn cancel(&mut self) {
+        if self.pending.take().is_some() {
+            self.stats.dropped += 1;
+        }
+    }
+
+    /// Count a frame that was due at `due` as sent at `now`, and schedule the next slot.
+    fn send(&mut self, due: Instant, now: Instant) {
+        self.stats.sent += 1;
+        if now - due > self.interval {
+            self.stats.late += 1;
+        }
+        let next = due + self.interval;
+        // Running behind, so skip ahead rather than trying to catch up.
+        self.next = Some(if next > now {
+            next
+        } else {
+            now + self.interval
+        });
+    }
+}
+
+/// A device whose `array_update` calls are paced by a [`FramePacer`]. Updates that come too
+/// quickly are held back, and the color array as it is by then is sent by the next update
+/// that finds its slot has come, or by [`poll`](Self::poll) or [`flush`](Self::flush).
+///
+/// Changes are only held back when they are applied with `array_update`. While the
+/// auto-update trigger is set, the device applies every change right away.
+#[derive(Debug)]
+pub struct PacedDevice<D> {
+    device: D,
+    pacer: FramePacer,
+}
+
+impl<D: RgbDevice> PacedDevice<D> {
+    /// Pace the updates of `device` with `pacer`.
+    pub fn new(device: D, pacer: FramePacer) -> Self {
+        Self { device, pacer }
+    }
+
+    /// What happened to the frames updated so far.
+    pub fn stats(&self) -> PacingStats {
+        self.pacer.stats()
+    }
+
+    /// Send the frame that was held back if it is due. Returns `false` if the device could not
+    /// be updated.
+    pub fn poll(&mut self) -> bool {
+        !self.pacer.poll() || self.device.array_update()
+    }
+
+    /// Wait until the frame that was held back is due and send it. Returns `false` if the
+    /// device could not be updated.
+    pub fn flush(&mut self) -> bool {
+        !self.pacer.flush() || self.device.array_update()
+    }
+
+    /// Access the paced device.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    /// Stop pacing and return the device. A frame that was held back is not sent.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D: RgbDevice> RgbDevice for PacedDevice<D> {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.device
+            .direct_set_key_matrix(row_and_column, red, green, blue)
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        self.device.direct_reset_key_matrix(row_and_column)
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.device
+            .array_set_single_matrix(row_and_column, red, green, blue)
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.device.array_set_frame(frame)
+    }
+
+    /// Send the color array if its slot has come, or hold it back until then. Returns `false`
+    /// if the device could not be updated.
+    fn array_update(&mut self) -> bool {
+        !self.pacer.submit() || self.device.array_update()
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        self.device.array_auto_update(auto_update)
+    }
+
+    /// Reset the device, dropping the frame that was held back.
+    fn reset_all(&mut self) -> bool {
+        self.pacer.cancel();
+        self.device.reset_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A device that takes `delay` to apply the color array.
+    struct Slow {
+        delay: Duration,
+        updates: u32,
+    }
+
+    impl Slow {
+        fn new(delay: Duration) -> Self {
+            Self { delay, updates: 0 }
+        }
+    }
+
+    impl RgbDevice for Slow {
+        fn direct_set_key_matrix(&mut self, _: (u8, u8), _: u8, _: u8, _: u8) -> bool {
+            true
+        }
+
+        fn direct_reset_key_matrix(&mut self, _: (u8, u8)) -> bool {
+            true
+        }
+
+        fn array_set_single_matrix(&mut self, _: (u8, u8), _: u8, _: u8, _: u8) -> bool {
+            true
+        }
+
+        fn array_set_frame(&mut self, _: &Frame) -> bool {
+            true
+        }
+
+        fn array_update(&mut self) -> bool {
+            sleep(self.delay);
+            self.updates += 1;
+            true
+        }
+
+        fn array_auto_update(&mut self, _: bool) {}
+
+        fn reset_all(&mut self) -> bool {
+            true
+        }
+    }
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn dropped() {
+        let mut device =
+            PacedDevice::new(Slow::new(INTERVAL / 4), FramePacer::with_interval(INTERVAL));
+        for _ in 0..4 {
+            assert!(device.array_update());
+        }
+        // The first update went out, the next two were replaced before their slot and the last
+        // one is held back.
+        assert_eq!(device.get_mut().updates, 1);
+        assert!(device.flush());
+        assert_eq!(device.get_mut().updates, 2);
+        let stats = PacingStats {
+            sent: 2,
+            dropped: 2,
+            late: 0,
+        };
+        assert_eq!(device.stats(), stats);
+
+        // Nothing is held back any more.
+        assert!(device.flush());
+        assert_eq!(device.get_mut().updates, 2);
+
+        // Resetting drops what is held back.
+        assert!(device.array_update());
+        assert!(device.reset_all());
+        assert!(device.flush());
+        assert_eq!(device.stats().dropped, 3);
+    }
+
+    #[test]
+    fn late() {
+        // Updating takes longer than an interval, so every frame after the first is sent more
+        // than an interval after its slot.
+        let mut device = Slow::new(INTERVAL * 3);
+        let mut pacer = FramePacer::with_interval(INTERVAL);
+        for _ in 0..4 {
+            pacer.wait();
+            assert!(pacer.submit());
+            device.array_update();
+        }
+        let stats = PacingStats {
+            sent: 4,
+            dropped: 0,
+            late: 3,
+        };
+        assert_eq!(pacer.stats(), stats);
+
+        // A frame that is held back is late when it is only sent well after it was due.
+        let mut device = PacedDevice::new(
+            Slow::new(Duration::ZERO),
+            FramePacer::with_interval(INTERVAL),
+        );
+        assert!(device.array_update());
+        assert!(device.array_update());
+        assert!(device.poll());
+        assert_eq!(device.get_mut().updates, 1);
+        sleep(INTERVAL * 3);
+        assert!(device.poll());
+        assert_eq!(device.get_mut().updates, 2);
+        assert_eq!(device.stats().late, 1);
+    }
+}
@@ -0,0 +1,217 @@
+//! Keep the LEDs from drawing more current than the USB port can supply.
+//!
+//! Every key lit up white draws a lot of current, which a bus-powered hub may not be able to
+//! supply for long. With a [`PowerBudget`] set on the keyboard with
+//! [`RgbKeyboard::set_power_budget`](crate::RgbKeyboard::set_power_budget), every frame of the
+//! color array is estimated before it is sent and dimmed as a whole if it would exceed the
+//! budget. The estimate is rough, as the current depends on the LEDs and the firmware, so the
+//! current per channel can be tuned to measurements.
+//!
+//! Only the color array is limited. Colors set directly with
+//! [`RgbKeyboard::direct_set_key`](crate::RgbKeyboard::direct_set_key) are sent as they are and
+//! aren't counted in the estimate, so a few of them on top of a frame that uses the whole
+//! budget can exceed it.
+//!
+//! ```rust,no_run
+//! use wooting_rgb::{power::PowerBudget, Frame, RgbKeyboard};
+//!
+//! let mut keyboard = RgbKeyboard::open().unwrap();
+//! keyboard
+//!     .set_power_budget(Some(PowerBudget::new(300.0)))
+//!     .unwrap();
+//! keyboard.array_set_frame(&Frame::filled((255, 255, 255)));
+//! keyboard.array_update();
+//!
+//! if let Some(report) = keyboard.power_report() {
+//!     println!("Scaled by {:.2} to {:.0} mA", report.scale, report.milliamps);
+//! }
+//! ```
+
+use crate::{Frame, WootingError, COLUMNS, ROWS};
+
+/// The current a single channel of an LED is estimated to draw at full brightness.
+pub const DEFAULT_CHANNEL_MILLIAMPS: f32 = 5.0;
+
+/// How much current the LEDs may draw together. The keyboard only takes budgets that pass
+/// [`check`](Self::check).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerBudget {
+    /// The most current every LED together may draw, in milliamps.
+    pub max_milliamps: f32,
+    /// The current a single LED draws at full brightness on its red, green and blue channel,
+    /// in milliamps. The current is taken to grow linearly with the value sent.
+    pub channel_milliamps: (f32, f32, f32),
+}
+
+/// How a frame was estimated and limited to the [`PowerBudget`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerReport {
+    /// The current the frame would have drawn, in milliamps.
+    pub estimated_milliamps: f32,
+    /// The current the frame draws as it was sent, in milliamps.
+    pub milliamps: f32,
+    /// What every color was scaled by, `1.0` if the frame was within the budget.
+    pub scale: f32,
+}
+
+impl PowerReport {
+    /// Was the frame dimmed to stay within the budget?
+    pub fn is_limited(&self) -> bool {
+        self.scale < 1.0
+    }
+}
+
+impl PowerBudget {
+    /// Allow `max_milliamps` for every LED together, with the default estimate per channel.
+    pub fn new(max_milliamps: f32) -> Self {
+        Self {
+            max_milliamps,
+            channel_milliamps: (
+                DEFAULT_CHANNEL_MILLIAMPS,
+                DEFAULT_CHANNEL_MILLIAMPS,
+                DEFAULT_CHANNEL_MILLIAMPS,
+            ),
+        }
+    }
+
+    /// Make sure the budget can limit frames: the most current must be a finite number above
+    /// zero, and the current per channel finite and not negative. Fails with
+    /// [`WootingError::InvalidPowerBudget`] otherwise.
+    ///
+    /// ```rust
+    /// use wooting_rgb::power::PowerBudget;
+    ///
+    /// assert!(PowerBudget::new(300.0).check().is_ok());
+    /// assert!(PowerBudget::new(f32::NAN).check().is_err());
+    /// assert!(PowerBudget::new(-1.0).check().is_err());
+    /// ```
+    pub fn check(&self) -> Result<(), WootingError> {
+        let (red, green, blue) = self.channel_milliamps;
+        let valid = self.max_milliamps.is_finite()
+            && self.max_milliamps > 0.0
+            && [red, green, blue]
+                .iter()
+                .all(|milliamps| milliamps.is_finite() && *milliamps >= 0.0);
+        if valid {
+            Ok(())
+        } else {
+            Err(WootingError::InvalidPowerBudget)
+        }
+    }
+
+    /// Estimate the current a frame draws, in milliamps. Every position in the matrix is
+    /// counted, so keyboards with fewer LEDs are estimated on the safe side.
+    ///
+    /// ```rust
+    /// use wooting_rgb::{power::PowerBudget, Frame, Key};
+    ///
+    /// let budget = PowerBudget::new(300.0);
+    /// let mut frame = Frame::default();
+    /// frame.set(Key::A, (255, 0, 255));
+    /// assert_eq!(budget.estimate(&frame), 10.0);
+    /// ```
+    pub fn estimate(&self, frame: &Frame) -> f32 {
+        let mut milliamps = 0.0;
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let (red, green, blue) = frame.get((row as u8, column as u8)).unwrap_or_default();
+                milliamps += self.color(red as f32, green as f32, blue as f32);
+            }
+        }
+        milliamps
+    }
+
+    /// The current a color with channels from `0.0` to `255.0` draws, in milliamps.
+    pub(crate) fn color(&self, red: f32, green: f32, blue: f32) -> f32 {
+        let (red_milliamps, green_milliamps, blue_milliamps) = self.channel_milliamps;
+        (red * red_milliamps + green * green_milliamps + blue * blue_milliamps) / 255.0
+    }
+
+    /// How a frame drawing `milliamps` is scaled to stay within the budget.
+    pub(crate) fn limit(&self, milliamps: f32) -> PowerReport {
+        let scale = if milliamps > self.max_milliamps {
+            (self.max_milliamps / milliamps).max(0.0)
+        } else {
+            1.0
+        };
+        PowerReport {
+            estimated_milliamps: milliamps,
+            milliamps: milliamps * scale,
+            scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        let invalid = [
+            PowerBudget::new(0.0),
+            PowerBudget::new(-1.0),
+            PowerBudget::new(f32::NAN),
+            PowerBudget::new(f32::INFINITY),
+            PowerBudget {
+                channel_milliamps: (5.0, -5.0, 5.0),
+                ..PowerBudget::new(300.0)
+            },
+            PowerBudget {
+                channel_milliamps: (5.0, 5.0, f32::NAN),
+                ..PowerBudget::new(300.0)
+            },
+        ];
+        for budget in invalid {
+            assert_eq!(
+                budget.check(),
+                Err(WootingError::InvalidPowerBudget),
+                "{budget:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn estimate() {
+        let budget = PowerBudget {
+            max_milliamps: 300.0,
+            channel_milliamps: (1.0, 2.0, 3.0),
+        };
+        let mut frame = Frame::default();
+        assert_eq!(budget.estimate(&frame), 0.0);
+        frame.set((0, 0), (255, 255, 255));
+        frame.set((5, 20), (0, 0, 255));
+        assert_eq!(budget.estimate(&frame), 9.0);
+        assert_eq!(
+            PowerBudget::new(300.0).estimate(&Frame::filled((255, 255, 255))),
+            (ROWS * COLUMNS) as f32 * 15.0
+        );
+    }
+
+    #[test]
+    fn limit() {
+        let budget = PowerBudget::new(300.0);
+        let under = budget.limit(200.0);
+        assert_eq!(
+            under,
+            PowerReport {
+                estimated_milliamps: 200.0,
+                milliamps: 200.0,
+                scale: 1.0,
+            }
+        );
+        assert!(!under.is_limited());
+        assert_eq!(budget.limit(300.0).scale, 1.0);
+
+        let over = budget.limit(600.0);
+        assert_eq!(
+            over,
+            PowerReport {
+                estimated_milliamps: 600.0,
+                milliamps: 300.0,
+                scale: 0.5,
+            }
+        );
+        assert!(over.is_limited());
+    }
+}
@@ -0,0 +1,290 @@
+//! Preview frames in the terminal, drawn as a keyboard shaped grid of colored keys.
+//!
+//! The terminal has to support 24-bit ("truecolor") ANSI escape codes.
+
+use std::io::{self, Stdout, Write};
+
+use crate::{
+    pacing::FramePacer, Frame, IntoMatrixRowColumn, Key, RgbDevice, VirtualKeyboard, LAYOUT_WIDTH,
+};
+
+/// How many characters wide is one key unit?
+const CHARACTERS_PER_UNIT: f32 = 5.0;
+/// How many lines does a rendered frame take up?
+const LINES: usize = 6;
+
+/// Render a frame as lines of text with ANSI escape codes, one line per keyboard row. Every
+/// key is drawn in its color at its physical position and labelled with its name, cut short if
+/// the key is too narrow.
+///
+/// ```rust
+/// use wooting_rgb::{preview, Frame};
+///
+/// // Print the whole keyboard in purple.
+/// println!("{}", preview::render(&Frame::filled((128, 0, 255))));
+/// ```
+pub fn render(frame: &Frame) -> String {
+    let width = (LAYOUT_WIDTH * CHARACTERS_PER_UNIT).round() as usize;
+    let mut lines: Vec<Vec<(usize, Key)>> = vec![Vec::new(); LINES];
+    for key in Key::ALL {
+        let (row, _) = key.get_matrix_row_and_column();
+        let (x, ..) = key.physical_rect();
+        lines[row as usize].push(((x * CHARACTERS_PER_UNIT).round() as usize, key));
+    }
+
+    let mut output = String::new();
+    for keys in &mut lines {
+        keys.sort_by_key(|(start, _)| *start);
+        let mut cursor = 0;
+        for (start, key) in keys.iter() {
+            let (x, _, key_width, _) = key.physical_rect();
+            let end = ((x + key_width) * CHARACTERS_PER_UNIT).round() as usize;
+            // Leave a one character gap between keys.
+            let size = end.saturating_sub(*start + 1);
+            let (red, green, blue) = frame.get(*key).unwrap_or_default();
+            // Pick whichever label color is easier to read on the key color.
+            let luma = 299 * red as u32 + 587 * green as u32 + 114 * blue as u32;
+            let label = if luma > 128_000 { 0 } else { 255 };
+            let name: String = key.to_string().chars().take(size).collect();
+
+            output.push_str(&" ".repeat(start.saturating_sub(cursor)));
+            output.push_str(&format!(
+                "\x1b[48;2;{red};{green};{blue}m\x1b[38;2;{label};{label};{label}m{name:^size$}\x1b[0m"
+            ));
+            cursor = start + size;
+        }
+        output.push_str(&" ".repeat(width.saturating_sub(cursor)));
+        output.push('\n');
+    }
+    output
+}
+
+/// A device that shows what it is sent in the terminal instead of on a keyboard. It keeps
+/// track of the colors with a [`VirtualKeyboard`] and can be used anywhere a [`RgbDevice`] is
+/// expected.
+///
+/// In live mode the preview is redrawn in place when the colors that are shown change, at most
+/// [`DEFAULT_MAX_FPS`](crate::pacing::DEFAULT_MAX_FPS) times per second unless
+/// [`set_max_fps`](TerminalPreview::set_max_fps) says otherwise. Changes that come quicker are
+/// drawn by the next call that finds their slot has come, by [`flush`](TerminalPreview::flush)
+/// or when the preview is dropped. Otherwise it is only drawn when [`TerminalPreview::draw`] is
+/// called.
+///
+/// ```rust,no_run
+/// use wooting_rgb::{preview::TerminalPreview, Key, RgbDevice};
+///
+/// let mut preview = TerminalPreview::stdout();
+/// preview.set_live(true);
+/// // Light up the A key, which redraws the preview.
+/// preview.array_set_single(Key::A, 255, 255, 255);
+/// preview.array_update();
+/// ```
+#[derive(Debug)]
+pub struct TerminalPreview<W: Write = Stdout> {
+    keyboard: VirtualKeyboard,
+    writer: W,
+    live: bool,
+    /// Limits how often live mode redraws.
+    pacer: FramePacer,
+    /// The frame that was drawn last, if any, so the next one can be drawn over it.
+    drawn: Option<Frame>,
+}
+
+impl TerminalPreview<Stdout> {
+    /// Create a preview that draws to standard output.
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> TerminalPreview<W> {
+    /// Create a preview that draws to the given writer.
+    pub fn new(writer: W) -> Self {
+        Self {
+            keyboard: VirtualKeyboard::default(),
+            writer,
+            live: false,
+            pacer: FramePacer::default(),
+            drawn: None,
+        }
+    }
+
+    /// Enable or disable live mode. By default live mode is disabled.
+    pub fn set_live(&mut self, live: bool) {
+        self.live = live;
+    }
+
+    /// Redraw at most `max_fps` times per second in live mode.
+    pub fn set_max_fps(&mut self, max_fps: u32) {
+        self.pacer = FramePacer::new(max_fps);
+    }
+
+    /// Return the virtual keyboard that keeps track of the colors.
+    pub fn keyboard(&self) -> &VirtualKeyboard {
+        &self.keyboard
+    }
+
+    /// Draw the colors that are currently shown. If the preview was drawn before, the new
+    /// drawing replaces the previous one.
+    pub fn draw(&mut self) -> io::Result<()> {
+        let frame = self.keyboard.frame();
+        if self.drawn.is_some() {
+            // Move the cursor back up to the start of the previous drawing.
+            write!(self.writer, "\x1b[{LINES}A\r")?;
+        }
+        self.writer.write_all(render(&frame).as_bytes())?;
+        self.writer.flush()?;
+        self.drawn = Some(frame);
+        Ok(())
+    }
+
+    /// Wait until the change that live mode held back is due and draw it, if there is one.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.pacer.flush() {
+            self.draw()?;
+        }
+        Ok(())
+    }
+
+    /// In live mode, redraw if the colors that are shown have changed and the slot for the
+    /// next drawing has come. Returns `false` if drawing failed.
+    fn refresh(&mut self) -> bool {
+        if !self.live || self.drawn == Some(self.keyboard.frame()) {
+            return true;
+        }
+        !self.pacer.submit() || self.draw().is_ok()
+    }
+}
+
+impl<W: Write> Drop for TerminalPreview<W> {
+    /// Draw the change that live mode held back, so the preview ends up showing the last colors.
+    fn drop(&mut self) {
+        if self.pacer.due().is_some() {
+            let _ = self.draw();
+        }
+    }
+}
+
+impl<W: Write> RgbDevice for TerminalPreview<W> {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.keyboard
+            .direct_set_key_matrix(row_and_column, red, green, blue)
+            && self.refresh()
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        self.keyboard.direct_reset_key_matrix(row_and_column) && self.refresh()
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.keyboard
+            .array_set_single_matrix(row_and_column, red, green, blue)
+            && self.refresh()
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.keyboard.array_set_frame(frame) && self.refresh()
+    }
+
+    fn array_update(&mut self) -> bool {
+        self.keyboard.array_update() && self.refresh()
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        self.keyboard.array_auto_update(auto_update)
+    }
+
+    fn reset_all(&mut self) -> bool {
+        self.keyboard.reset_all() && self.refresh()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drop the escape codes from a rendered line.
+    fn text(line: &str) -> String {
+        let mut text = String::new();
+        let mut escape = false;
+        for character in line.chars() {
+            match character {
+                '\x1b' => escape = true,
+                'm' if escape => escape = false,
+                _ if !escape => text.push(character),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn render_frame() {
+        let mut frame = Frame::default();
+        frame.set(Key::Escape, (255, 0, 0));
+        frame.set(Key::Space, (255, 255, 255));
+        let rendered = render(&frame);
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines.len(), LINES);
+
+        // The escape key is dark enough for a white label, the space bar needs a black one.
+        let escape = Key::Escape.to_string();
+        assert!(lines[0].starts_with(&format!(
+            "\x1b[48;2;255;0;0m\x1b[38;2;255;255;255m{escape:^4}\x1b[0m"
+        )));
+        assert!(lines[5].contains("\x1b[48;2;255;255;255m\x1b[38;2;0;0;0m"));
+        assert!(lines[1].starts_with("\x1b[48;2;0;0;0m\x1b[38;2;255;255;255m"));
+
+        // Every line is as wide as the keyboard, once the escape codes are dropped.
+        let width = (LAYOUT_WIDTH * CHARACTERS_PER_UNIT).round() as usize;
+        for line in lines {
+            assert_eq!(text(line).chars().count(), width);
+        }
+    }
+
+    #[test]
+    fn live() {
+        let mut preview = TerminalPreview::new(Vec::new());
+        preview.set_max_fps(10);
+
+        // Nothing is drawn until live mode is enabled, and then by the next call.
+        preview.array_set_single(Key::A, 255, 255, 255);
+        preview.array_update();
+        assert!(preview.writer.is_empty());
+        preview.set_live(true);
+        preview.array_set_single(Key::S, 255, 255, 255);
+        let mut frame = Frame::default();
+        frame.set(Key::A, (255, 255, 255));
+        assert_eq!(
+            String::from_utf8(preview.writer.clone()).unwrap(),
+            render(&frame)
+        );
+
+        // The next changes come before the next slot, so they are held back and drawn at once
+        // over the previous drawing.
+        preview.writer.clear();
+        preview.array_update();
+        preview.direct_set_key(Key::D, 255, 255, 255);
+        assert!(preview.writer.is_empty());
+        preview.flush().unwrap();
+        frame.set(Key::S, (255, 255, 255));
+        frame.set(Key::D, (255, 255, 255));
+        let expected = format!("\x1b[{LINES}A\r{}", render(&frame));
+        assert_eq!(String::from_utf8(preview.writer.clone()).unwrap(), expected);
+        preview.writer.clear();
+        preview.flush().unwrap();
+        assert!(preview.writer.is_empty());
+    }
+}
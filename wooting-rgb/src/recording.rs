@@ -0,0 +1,480 @@
+//! Record the operations sent to a device and replay them later.
+//!
+//! Recordings are stored in a compact binary format: a `WRGB` header and format version,
+//! followed by one entry per operation holding the time since the previous operation in
+//! microseconds (as a LEB128 variable length integer), an operation tag and its arguments.
+//! Color arrays are stored as the colors of every [`Key`] in [`Key::ALL`] order.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    animation::{check_speed, scale_delay},
+    Frame, Key, RgbDevice, WootingError,
+};
+
+/// The bytes every recording starts with.
+const MAGIC: &[u8; 4] = b"WRGB";
+/// The version of the recording format.
+const VERSION: u8 = 1;
+
+/// An operation sent to a device.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Event {
+    /// A key was set with [`RgbDevice::direct_set_key_matrix`].
+    DirectSetKey((u8, u8), (u8, u8, u8)),
+    /// A key was reset with [`RgbDevice::direct_reset_key_matrix`].
+    DirectResetKey((u8, u8)),
+    /// A color in the array was set with [`RgbDevice::array_set_single_matrix`].
+    ArraySetSingle((u8, u8), (u8, u8, u8)),
+    /// The color array was set with [`RgbDevice::array_set_frame`].
+    ArraySetFrame(Box<Frame>),
+    /// The color array was applied with [`RgbDevice::array_update`].
+    ArrayUpdate,
+    /// Auto-update was changed with [`RgbDevice::array_auto_update`].
+    ArrayAutoUpdate(bool),
+    /// All colors were reset with [`RgbDevice::reset_all`].
+    ResetAll,
+}
+
+impl Event {
+    /// Send this operation to a device, returning what the device returned.
+    pub fn apply<D: RgbDevice + ?Sized>(&self, device: &mut D) -> bool {
+        match *self {
+            Event::DirectSetKey(position, (red, green, blue)) => {
+                device.direct_set_key_matrix(position, red, green, blue)
+            }
+            Event::DirectResetKey(position) => device.direct_reset_key_matrix(position),
+            Event::ArraySetSingle(position, (red, green, blue)) => {
+                device.array_set_single_matrix(position, red, green, blue)
+            }
+            Event::ArraySetFrame(ref frame) => device.array_set_frame(frame),
+            Event::ArrayUpdate => device.array_update(),
+            Event::ArrayAutoUpdate(auto_update) => {
+                device.array_auto_update(auto_update);
+                true
+            }
+            Event::ResetAll => device.reset_all(),
+        }
+    }
+}
+
+/// A list of operations along with when they happened, relative to the start of the
+/// recording.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Recording {
+    events: Vec<(Duration, Event)>,
+}
+
+impl Recording {
+    /// Return the recorded operations along with when they happened.
+    pub fn events(&self) -> &[(Duration, Event)] {
+        &self.events
+    }
+
+    /// Add an operation that happened at the given time. Operations must be added in order.
+    pub fn push(&mut self, at: Duration, event: Event) {
+        self.events.push((at, event));
+    }
+
+    /// How long the recording lasts, from its start until the last operation.
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |(at, _)| *at)
+    }
+
+    /// Send every operation to a device, waiting between operations as long as they were
+    /// apart when recorded. A `speed` of `2.0` replays twice as fast, and returns
+    /// [`WootingError::InvalidSpeed`] unless it is a number greater than zero that is large
+    /// enough for the recording to last no longer than a [`Duration`] can hold.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{recording::Recording, RgbKeyboard};
+    ///
+    /// let mut keyboard = RgbKeyboard::open().unwrap();
+    /// // Load a recording a teammate sent over..
+    /// let recording = Recording::open("bug.wrgb").unwrap();
+    /// // ..and watch it at half speed.
+    /// recording.replay(&mut keyboard, 0.5).unwrap();
+    /// ```
+    pub fn replay<D: RgbDevice + ?Sized>(
+        &self,
+        device: &mut D,
+        speed: f32,
+    ) -> Result<(), WootingError> {
+        self.replay_while(device, speed, || true)
+    }
+
+    /// Replay like [`replay`](Self::replay), but stop before the next operation once `running`
+    /// returns `false`.
+    pub fn replay_while<D: RgbDevice + ?Sized, F: FnMut() -> bool>(
+        &self,
+        device: &mut D,
+        speed: f32,
+        mut running: F,
+    ) -> Result<(), WootingError> {
+        let speed = check_speed(speed)?;
+        // Fail before replaying anything if the last operation would come too late.
+        scale_delay(self.duration(), speed)?;
+        let start = Instant::now();
+        for (at, event) in &self.events {
+            if let Some(wait) = scale_delay(*at, speed)?.checked_sub(start.elapsed()) {
+                sleep(wait);
+            }
+            if !running() {
+                break;
+            }
+            event.apply(device);
+        }
+        Ok(())
+    }
+
+    /// Read a recording from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Write the recording to a file, replacing it if it exists.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read a recording in the binary recording format.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a recording"));
+        }
+        if header[4] != VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Unsupported recording version",
+            ));
+        }
+
+        let mut recording = Self::default();
+        let mut at = Duration::ZERO;
+        while let Some(delta) = read_varint(&mut reader)? {
+            at = at
+                .checked_add(Duration::from_micros(delta))
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Timestamp is too large"))?;
+
+            let event = match read_bytes::<1>(&mut reader)?[0] {
+                0 => {
+                    let [row, column, red, green, blue] = read_bytes(&mut reader)?;
+                    Event::DirectSetKey((row, column), (red, green, blue))
+                }
+                1 => {
+                    let [row, column] = read_bytes(&mut reader)?;
+                    Event::DirectResetKey((row, column))
+                }
+                2 => {
+                    let [row, column, red, green, blue] = read_bytes(&mut reader)?;
+                    Event::ArraySetSingle((row, column), (red, green, blue))
+                }
+                3 => {
+                    let mut frame = Frame::default();
+                    for key in Key::ALL {
+                        let [red, green, blue] = read_bytes(&mut reader)?;
+                        frame.set(key, (red, green, blue));
+                    }
+                    Event::ArraySetFrame(Box::new(frame))
+                }
+                4 => Event::ArrayUpdate,
+                5 => Event::ArrayAutoUpdate(read_bytes::<1>(&mut reader)?[0] != 0),
+                6 => Event::ResetAll,
+                _ => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "Unknown recording event",
+                    ))
+                }
+            };
+            recording.push(at, event);
+        }
+        Ok(recording)
+    }
+
+    /// Write the recording in the binary recording format.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        let mut previous = Duration::ZERO;
+        for (at, event) in &self.events {
+            let delta = at.saturating_sub(previous);
+            previous = *at;
+            write_varint(&mut writer, delta.as_micros() as u64)?;
+
+            match *event {
+                Event::DirectSetKey((row, column), (red, green, blue)) => {
+                    writer.write_all(&[0, row, column, red, green, blue])?
+                }
+                Event::DirectResetKey((row, column)) => writer.write_all(&[1, row, column])?,
+                Event::ArraySetSingle((row, column), (red, green, blue)) => {
+                    writer.write_all(&[2, row, column, red, green, blue])?
+                }
+                Event::ArraySetFrame(ref frame) => {
+                    writer.write_all(&[3])?;
+                    for (_, (red, green, blue)) in frame.iter() {
+                        writer.write_all(&[red, green, blue])?;
+                    }
+                }
+                Event::ArrayUpdate => writer.write_all(&[4])?,
+                Event::ArrayAutoUpdate(auto_update) => writer.write_all(&[5, auto_update as u8])?,
+                Event::ResetAll => writer.write_all(&[6])?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a device and records every operation sent to it, while still passing them on.
+///
+/// ```rust
+/// use wooting_rgb::{recording::Recorder, Key, RgbDevice, VirtualKeyboard};
+///
+/// let mut recorder = Recorder::new(VirtualKeyboard::default());
+/// recorder.array_set_single(Key::A, 255, 255, 255);
+/// recorder.array_update();
+///
+/// // Replaying the recording on another device gives the same result.
+/// let (keyboard, recording) = recorder.finish();
+/// let mut replayed = VirtualKeyboard::default();
+/// recording.replay(&mut replayed, 1.0).unwrap();
+/// assert_eq!(keyboard.frame(), replayed.frame());
+/// ```
+#[derive(Debug)]
+pub struct Recorder<D: RgbDevice> {
+    device: D,
+    recording: Recording,
+    start: Instant,
+}
+
+impl<D: RgbDevice> Recorder<D> {
+    /// Start recording the operations sent to a device.
+    pub fn new(device: D) -> Self {
+        Self {
+            device,
+            recording: Recording::default(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Return the wrapped device.
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Return what has been recorded so far.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Stop recording, returning the wrapped device and the recording.
+    pub fn finish(self) -> (D, Recording) {
+        (self.device, self.recording)
+    }
+
+    fn record(&mut self, event: Event) -> bool {
+        let at = self.start.elapsed();
+        let result = event.apply(&mut self.device);
+        self.recording.push(at, event);
+        result
+    }
+}
+
+impl<D: RgbDevice> RgbDevice for Recorder<D> {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.record(Event::DirectSetKey(row_and_column, (red, green, blue)))
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        self.record(Event::DirectResetKey(row_and_column))
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.record(Event::ArraySetSingle(row_and_column, (red, green, blue)))
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        self.record(Event::ArraySetFrame(Box::new(*frame)))
+    }
+
+    fn array_update(&mut self) -> bool {
+        self.record(Event::ArrayUpdate)
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        self.record(Event::ArrayAutoUpdate(auto_update));
+    }
+
+    fn reset_all(&mut self) -> bool {
+        self.record(Event::ResetAll)
+    }
+}
+
+/// Read exactly `N` bytes.
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read an unsigned LEB128 variable length integer, or `None` at the end of the input.
+fn read_varint(reader: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            return match shift {
+                0 => Ok(None),
+                _ => Err(ErrorKind::UnexpectedEof.into()),
+            };
+        }
+        // The last byte only has room for the highest bit.
+        if shift == 63 && byte[0] > 1 {
+            break;
+        }
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::InvalidData,
+        "Timestamp is too large",
+    ))
+}
+
+/// Write an unsigned LEB128 variable length integer.
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut frame = Frame::default();
+        frame.set(Key::A, (1, 2, 3));
+        frame.set(Key::Escape, (255, 128, 0));
+
+        let mut recording = Recording::default();
+        recording.push(Duration::ZERO, Event::ResetAll);
+        recording.push(
+            Duration::from_micros(5),
+            Event::DirectSetKey((1, 2), (3, 4, 5)),
+        );
+        recording.push(Duration::from_millis(20), Event::DirectResetKey((1, 2)));
+        recording.push(
+            Duration::from_millis(300),
+            Event::ArraySetSingle((5, 20), (255, 0, 255)),
+        );
+        recording.push(
+            Duration::from_secs(4),
+            Event::ArraySetFrame(Box::new(frame)),
+        );
+        recording.push(Duration::from_secs(4), Event::ArrayUpdate);
+        recording.push(Duration::from_secs(600), Event::ArrayAutoUpdate(true));
+        recording.push(Duration::from_secs(600), Event::ArrayAutoUpdate(false));
+
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        assert_eq!(&bytes[..5], b"WRGB\x01");
+        assert_eq!(Recording::read_from(&bytes[..]).unwrap(), recording);
+    }
+
+    #[test]
+    fn empty() {
+        let mut bytes = Vec::new();
+        Recording::default().write_to(&mut bytes).unwrap();
+        assert_eq!(
+            Recording::read_from(&bytes[..]).unwrap(),
+            Recording::default()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let error = |bytes: &[u8]| Recording::read_from(bytes).unwrap_err().kind();
+        assert_eq!(error(b"WRGX\x01"), ErrorKind::InvalidData);
+        assert_eq!(error(b"WRGB\x02"), ErrorKind::InvalidData);
+        assert_eq!(error(b"WRGB\x01\x00\x07"), ErrorKind::InvalidData);
+        // Cut off in the middle of an event.
+        assert_eq!(error(b"WRGB\x01\x00\x00\x01"), ErrorKind::UnexpectedEof);
+        assert_eq!(error(b"WRGB\x01\x80"), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn varint() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value).unwrap();
+            assert_eq!(read_varint(&mut &bytes[..]).unwrap(), Some(value));
+        }
+
+        // One bit more than fits.
+        let mut bytes = vec![0xff; 9];
+        bytes.push(0x02);
+        let error = read_varint(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        // Longer than any u64.
+        let bytes = [0x80; 11];
+        let error = read_varint(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replay() {
+        let mut recording = Recording::default();
+        recording.push(Duration::ZERO, Event::ArraySetSingle((0, 0), (1, 2, 3)));
+        recording.push(Duration::ZERO, Event::ArrayUpdate);
+
+        let mut keyboard = crate::VirtualKeyboard::default();
+        for speed in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(recording.replay(&mut keyboard, speed).is_err());
+        }
+        assert_eq!(keyboard.frame(), Frame::default());
+
+        // A timestamp from a file can be far off, which a slow speed stretches even further.
+        let mut late = recording.clone();
+        late.push(Duration::from_secs(u64::MAX / 2), Event::ArrayUpdate);
+        assert_eq!(
+            late.replay(&mut keyboard, 1e-3),
+            Err(WootingError::InvalidSpeed)
+        );
+        assert_eq!(keyboard.frame(), Frame::default());
+
+        recording.replay(&mut keyboard, 1.0).unwrap();
+        assert_eq!(keyboard.frame().get((0, 0)), Some((1, 2, 3)));
+    }
+}
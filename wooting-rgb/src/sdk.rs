@@ -0,0 +1,94 @@
+//! The calls into the SDK, which keeps its state globally and isn't safe to call from several
+//! threads at once. Every call goes through an [`Sdk`] handle, and only one of those exists at
+//! a time in the process. The calls that change the colors are a [`Backend`], which the tests
+//! replace with one that records them.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use wooting_rgb_sys::WOOTING_USB_META;
+
+use crate::Frame;
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Exclusive access to the SDK, for as long as it is kept. Hold on to it across calls that
+/// belong together, such as setting the color array and applying it.
+pub(crate) struct Sdk {
+    _guard: MutexGuard<'static, ()>,
+}
+
+/// Wait for the other threads to finish with the SDK and take it.
+///
+/// The SDK calls the disconnected callback from within these calls, so the callback must not
+/// take it again.
+pub(crate) fn lock() -> Sdk {
+    // A panic while holding the lock leaves nothing half-done on this side.
+    let guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    Sdk { _guard: guard }
+}
+
+impl Sdk {
+    pub fn kbd_connected(&mut self) -> bool {
+        unsafe { wooting_rgb_sys::wooting_rgb_kbd_connected() }
+    }
+
+    pub fn set_disconnected_cb(&mut self, callback: extern "C" fn()) {
+        unsafe { wooting_rgb_sys::wooting_rgb_set_disconnected_cb(Some(callback)) }
+    }
+
+    pub fn device_count(&mut self) -> u8 {
+        unsafe { wooting_rgb_sys::wooting_usb_device_count() }
+    }
+
+    pub fn select_device(&mut self, index: u8) -> bool {
+        unsafe { wooting_rgb_sys::wooting_usb_select_device(index) }
+    }
+
+    /// The SDK's information about the selected device, which it may change on the next call.
+    pub fn device_info(&mut self) -> Option<&WOOTING_USB_META> {
+        unsafe { wooting_rgb_sys::wooting_rgb_device_info().as_ref() }
+    }
+}
+
+/// The SDK calls that change the colors on the keyboard.
+pub(crate) trait Backend {
+    fn reset(&mut self) -> bool;
+    fn direct_set_key(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool;
+    fn direct_reset_key(&mut self, row: u8, column: u8) -> bool;
+    fn array_update_keyboard(&mut self) -> bool;
+    fn array_auto_update(&mut self, auto_update: bool);
+    fn array_set_single(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool;
+    fn array_set_full(&mut self, frame: &Frame) -> bool;
+}
+
+impl Backend for Sdk {
+    fn reset(&mut self) -> bool {
+        unsafe { wooting_rgb_sys::wooting_rgb_reset() }
+    }
+
+    fn direct_set_key(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool {
+        let (red, green, blue) = color;
+        unsafe { wooting_rgb_sys::wooting_rgb_direct_set_key(row, column, red, green, blue) }
+    }
+
+    fn direct_reset_key(&mut self, row: u8, column: u8) -> bool {
+        unsafe { wooting_rgb_sys::wooting_rgb_direct_reset_key(row, column) }
+    }
+
+    fn array_update_keyboard(&mut self) -> bool {
+        unsafe { wooting_rgb_sys::wooting_rgb_array_update_keyboard() }
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        unsafe { wooting_rgb_sys::wooting_rgb_array_auto_update(auto_update) }
+    }
+
+    fn array_set_single(&mut self, row: u8, column: u8, color: (u8, u8, u8)) -> bool {
+        let (red, green, blue) = color;
+        unsafe { wooting_rgb_sys::wooting_rgb_array_set_single(row, column, red, green, blue) }
+    }
+
+    fn array_set_full(&mut self, frame: &Frame) -> bool {
+        unsafe { wooting_rgb_sys::wooting_rgb_array_set_full(frame.to_buffer().as_ptr()) }
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use crate::{Frame, IntoMatrixRowColumn, RgbDevice, RgbKeyboard, WootingError};
+
+/// The keyboard every [`SharedRgbKeyboard`] refers to, for as long as any of them is alive.
+static KEYBOARD: Mutex<Weak<Mutex<RgbKeyboard>>> = Mutex::new(Weak::new());
+
+/// A handle to the connected keyboard that can be cloned and shared between threads. Every
+/// handle refers to the same [`RgbKeyboard`], and only one of them talks to the SDK at a time.
+/// The keyboard is reset when the last handle is dropped.
+///
+/// ```rust,no_run
+/// use std::thread;
+///
+/// use wooting_rgb::{Key, SharedRgbKeyboard};
+///
+/// let keyboard = SharedRgbKeyboard::open().unwrap();
+/// let handle = keyboard.clone();
+/// thread::spawn(move || handle.direct_set_key(Key::A, 255, 255, 255))
+///     .join()
+///     .unwrap();
+/// // The keyboard keeps its colors until this last handle is dropped.
+/// keyboard.direct_set_key(Key::B, 255, 255, 255);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedRgbKeyboard(Arc<Mutex<RgbKeyboard>>);
+
+// Handles are only useful if they can be sent to and shared between threads.
+const _: () = {
+    const fn shareable<T: Send + Sync>() {}
+    shareable::<SharedRgbKeyboard>();
+};
+
+impl SharedRgbKeyboard {
+    /// Get a handle to the keyboard, shared with every other handle that is alive. Fails with
+    /// [`WootingError::AlreadyOpen`] while the keyboard is owned by an [`RgbKeyboard`] instead.
+    pub fn open() -> Result<Self, WootingError> {
+        let mut keyboard = KEYBOARD.lock().unwrap();
+        if let Some(shared) = keyboard.upgrade() {
+            return Ok(Self(shared));
+        }
+        let shared = Arc::new(Mutex::new(RgbKeyboard::open()?));
+        *keyboard = Arc::downgrade(&shared);
+        Ok(Self(shared))
+    }
+
+    /// Lock the keyboard for a series of operations that no other handle may interleave with,
+    /// such as setting the color array and applying it.
+    ///
+    /// ```rust,no_run
+    /// use wooting_rgb::{Frame, SharedRgbKeyboard};
+    ///
+    /// let keyboard = SharedRgbKeyboard::open().unwrap();
+    /// let mut locked = keyboard.lock();
+    /// locked.array_set_frame(&Frame::filled((255, 0, 0)));
+    /// locked.array_update();
+    /// ```
+    pub fn lock(&self) -> MutexGuard<'_, RgbKeyboard> {
+        self.0.lock().unwrap()
+    }
+
+    /// Set the color of a single key without influencing the color array. See
+    /// [`RgbKeyboard::direct_set_key`].
+    pub fn direct_set_key<K: IntoMatrixRowColumn>(
+        &self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.lock().direct_set_key(key, red, green, blue)
+    }
+
+    /// Reset the color of a single key. See [`RgbKeyboard::direct_reset_key`].
+    pub fn direct_reset_key<K: IntoMatrixRowColumn>(&self, key: K) -> bool {
+        self.lock().direct_reset_key(key)
+    }
+
+    /// Apply any updates made to the color array. See [`RgbKeyboard::array_update`].
+    pub fn array_update(&self) -> bool {
+        self.lock().array_update()
+    }
+
+    /// Set an auto-update trigger after every change to the color array. See
+    /// [`RgbKeyboard::array_auto_update`].
+    pub fn array_auto_update(&self, auto_update: bool) {
+        self.lock().array_auto_update(auto_update)
+    }
+
+    /// Set a single color in the color array. See [`RgbKeyboard::array_set_single`].
+    pub fn array_set_single<K: IntoMatrixRowColumn>(
+        &self,
+        key: K,
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        self.lock().array_set_single(key, red, green, blue)
+    }
+
+    /// Set a complete color array. See [`RgbKeyboard::array_set_full`].
+    pub fn array_set_full<K: IntoMatrixRowColumn>(&self, array: &[(K, (u8, u8, u8))]) -> bool {
+        self.lock().array_set_full(array)
+    }
+
+    /// Set a complete color array from a [`Frame`]. See [`RgbKeyboard::array_set_frame`].
+    pub fn array_set_frame(&self, frame: &Frame) -> bool {
+        self.lock().array_set_frame(frame)
+    }
+
+    /// Restore all colors to those that were originally on the keyboard. See
+    /// [`RgbKeyboard::reset_all`].
+    pub fn reset_all(&self) -> bool {
+        self.lock().reset_all()
+    }
+}
+
+impl RgbDevice for SharedRgbKeyboard {
+    fn direct_set_key_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        SharedRgbKeyboard::direct_set_key(self, row_and_column, red, green, blue)
+    }
+
+    fn direct_reset_key_matrix(&mut self, row_and_column: (u8, u8)) -> bool {
+        SharedRgbKeyboard::direct_reset_key(self, row_and_column)
+    }
+
+    fn array_set_single_matrix(
+        &mut self,
+        row_and_column: (u8, u8),
+        red: u8,
+        green: u8,
+        blue: u8,
+    ) -> bool {
+        SharedRgbKeyboard::array_set_single(self, row_and_column, red, green, blue)
+    }
+
+    fn array_set_frame(&mut self, frame: &Frame) -> bool {
+        SharedRgbKeyboard::array_set_frame(self, frame)
+    }
+
+    fn array_update(&mut self) -> bool {
+        SharedRgbKeyboard::array_update(self)
+    }
+
+    fn array_auto_update(&mut self, auto_update: bool) {
+        SharedRgbKeyboard::array_auto_update(self, auto_update)
+    }
+
+    fn reset_all(&mut self) -> bool {
+        SharedRgbKeyboard::reset_all(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        tests::{Call, Recorder},
+        Key,
+    };
+
+    #[test]
+    fn last_handle_resets() {
+        let recorder = Recorder::default();
+        let mut keyboard = RgbKeyboard::open().unwrap();
+        keyboard.recorder = Some(recorder.clone());
+        let keyboard = SharedRgbKeyboard(Arc::new(Mutex::new(keyboard)));
+
+        // The handle is dropped along with the thread, which leaves the colors alone.
+        let handle = keyboard.clone();
+        let set = thread::spawn(move || handle.direct_set_key(Key::A, 255, 255, 255));
+        assert!(set.join().unwrap());
+        let (row, column) = Key::A.get_matrix_row_and_column();
+        assert_eq!(
+            recorder.take(),
+            [Call::DirectSetKey(row, column, (255, 255, 255))]
+        );
+        drop(keyboard.clone());
+        assert_eq!(recorder.take(), []);
+
+        drop(keyboard);
+        assert_eq!(recorder.take(), [Call::Reset, Call::AutoUpdate(false)]);
+        assert!(RgbKeyboard::open().is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+//! Render text on the keyboard, either by scrolling it across the key matrix with a small
+//! built-in bitmap font or by lighting the keys that spell it one after another.
+
+use std::time::Duration;
+
+use crate::{animation::Animation, Frame, Key, WootingError};
+
+/// How many matrix columns have keys in them?
+const VISIBLE_COLUMNS: usize = 17;
+/// How many rows tall is a glyph?
+const GLYPH_HEIGHT: usize = 5;
+/// How many columns wide is a glyph, before it is trimmed?
+const GLYPH_WIDTH: usize = 3;
+
+/// Return the bitmap for a character as rows from top to bottom, where the highest of the three
+/// used bits is the leftmost column. Characters without a glyph are drawn as `?`.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// Text rendered with the built-in font that scrolls across the keyboard from right to left.
+/// Glyphs are five rows tall and drawn from the function row down.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use wooting_rgb::{animation::PlaybackOptions, text::ScrollingText, RgbKeyboard};
+///
+/// let mut keyboard = RgbKeyboard::open().unwrap();
+/// // Scroll a red message across a black keyboard..
+/// let text = ScrollingText::new("ON AIR", (255, 0, 0), (0, 0, 0));
+/// // ..moving one column every 100 ms.
+/// text.to_animation(Duration::from_millis(100))
+///     .play(&mut keyboard, &PlaybackOptions::default())
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ScrollingText {
+    /// Every column of the rendered text, where bit `n` is set if row `n` is lit.
+    columns: Vec<u8>,
+    foreground: (u8, u8, u8),
+    background: (u8, u8, u8),
+}
+
+impl ScrollingText {
+    /// Render text with the given text and background colors. Letters are shown in upper case
+    /// and characters without a glyph are shown as `?`.
+    pub fn new(text: &str, foreground: (u8, u8, u8), background: (u8, u8, u8)) -> Self {
+        let mut columns = Vec::new();
+        for character in text.chars() {
+            let rows = glyph(character);
+            let mut glyph_columns: Vec<u8> = (0..GLYPH_WIDTH)
+                .map(|column| {
+                    let bit = GLYPH_WIDTH - 1 - column;
+                    rows.iter()
+                        .enumerate()
+                        .filter(|(_, row)| *row & (1 << bit) != 0)
+                        .fold(0, |mask, (row, _)| mask | (1 << row))
+                })
+                .collect();
+            // Narrow glyphs such as `!` only take up the columns they draw in.
+            if character != ' ' {
+                while glyph_columns.last() == Some(&0) {
+                    glyph_columns.pop();
+                }
+                while glyph_columns.first() == Some(&0) {
+                    glyph_columns.remove(0);
+                }
+            }
+            columns.extend(glyph_columns);
+            // Leave a gap between characters.
+            columns.push(0);
+        }
+
+        Self {
+            columns,
+            foreground,
+            background,
+        }
+    }
+
+    /// How many steps it takes for the text to scroll in from the right edge of the keyboard
+    /// until it has completely left on the left edge.
+    pub fn steps(&self) -> usize {
+        self.columns.len() + VISIBLE_COLUMNS
+    }
+
+    /// Render the text scrolled by the given number of columns. At step `0` the text is just
+    /// outside the right edge of the keyboard.
+    pub fn frame(&self, step: usize) -> Frame {
+        let mut frame = Frame::filled(self.background);
+        for column in 0..VISIBLE_COLUMNS {
+            let Some(mask) = (step + column)
+                .checked_sub(VISIBLE_COLUMNS)
+                .and_then(|index| self.columns.get(index))
+            else {
+                continue;
+            };
+            for row in 0..GLYPH_HEIGHT {
+                if mask & (1 << row) != 0 {
+                    frame.set((row as u8, column as u8), self.foreground);
+                }
+            }
+        }
+        frame
+    }
+
+    /// Render every step of the text scrolling past as an animation, moving one column every
+    /// `step`. Shorter steps scroll faster.
+    pub fn to_animation(&self, step: Duration) -> Animation {
+        Animation::new(
+            (0..self.steps())
+                .map(|index| (self.frame(index), step))
+                .collect(),
+        )
+    }
+}
+
+/// Return the keys that spell out the text, one key per character. Characters are looked up
+/// with [`Key`]'s `FromStr` implementation, so both letters and symbols such as `~` work.
+/// Returns [`WootingError::UnknownKey`] if a character has no key.
+///
+/// ```rust
+/// use wooting_rgb::{text, Key};
+///
+/// assert_eq!(text::spell("Hi!"), Err(wooting_rgb::WootingError::UnknownKey));
+/// assert_eq!(text::spell("Hi 1"), Ok(vec![Key::H, Key::I, Key::Space, Key::One]));
+/// ```
+pub fn spell(text: &str) -> Result<Vec<Key>, WootingError> {
+    text.chars()
+        .map(|character| character.to_string().parse())
+        .collect()
+}
+
+/// Light the keys that spell out the text one after another as an animation, each key shown
+/// for `delay`. Repeated letters go dark briefly so that every character is visible.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use wooting_rgb::{animation::PlaybackOptions, text, RgbKeyboard};
+///
+/// let mut keyboard = RgbKeyboard::open().unwrap();
+/// // Spell out a build name in green, one key every half second.
+/// text::spell_animation("release", (0, 255, 0), (0, 0, 0), Duration::from_millis(500))
+///     .unwrap()
+///     .play(&mut keyboard, &PlaybackOptions::default())
+///     .unwrap();
+/// ```
+pub fn spell_animation(
+    text: &str,
+    foreground: (u8, u8, u8),
+    background: (u8, u8, u8),
+    delay: Duration,
+) -> Result<Animation, WootingError> {
+    // A short blank frame between letters makes double letters distinguishable.
+    let gap = delay / 5;
+    let mut frames = Vec::new();
+    for key in spell(text)? {
+        let mut frame = Frame::filled(background);
+        frame.set(key, foreground);
+        frames.push((frame, delay - gap));
+        frames.push((Frame::filled(background), gap));
+    }
+    Ok(Animation::new(frames))
+}
@@ -0,0 +1,164 @@
+//! Set up the udev rules that let users access Wooting devices on Linux without root.
+//!
+//! ```rust,no_run
+//! use std::path::Path;
+//!
+//! use wooting_rgb::udev;
+//!
+//! // Needs root, after which udev has to reload its rules.
+//! udev::install_rules(Path::new(udev::RULES_PATH)).unwrap();
+//! ```
+
+use std::{ffi::CString, fmt::Write, fs, io, os::unix::ffi::OsStrExt, path::Path};
+
+use crate::usb::{self, LEGACY_PRODUCT_IDS, LEGACY_VENDOR_ID, VENDOR_ID};
+
+/// Where the rules are installed by default. They have to come before `73-seat-late.rules`,
+/// which grants access to the devices tagged `uaccess`.
+pub const RULES_PATH: &str = "/etc/udev/rules.d/70-wooting.rules";
+
+/// The udev rules that give the logged in user access to every Wooting device, both through
+/// hidraw and as a USB device. Other users can't open them.
+///
+/// ```rust
+/// assert!(wooting_rgb::udev::rules().contains(r#"ATTRS{idVendor}=="31e3""#));
+/// ```
+pub fn rules() -> String {
+    let mut rules = String::from(
+        "# Give the logged in user access to Wooting devices. Generated by wooting-rgb.\n",
+    );
+    let rule = |rules: &mut String, ids: &str| {
+        for subsystem in ["hidraw", "usb"] {
+            let _ = writeln!(
+                rules,
+                "SUBSYSTEM==\"{subsystem}\", {ids}, MODE=\"0660\", TAG+=\"uaccess\""
+            );
+        }
+    };
+
+    for (name, product_id) in ["Wooting One", "Wooting Two"]
+        .iter()
+        .zip(LEGACY_PRODUCT_IDS)
+    {
+        let _ = writeln!(rules, "\n# {name} with its original firmware");
+        rule(
+            &mut rules,
+            &format!(
+                "ATTRS{{idVendor}}==\"{LEGACY_VENDOR_ID:04x}\", \
+                 ATTRS{{idProduct}}==\"{product_id:04x}\""
+            ),
+        );
+    }
+    rules.push_str("\n# Every other Wooting device\n");
+    rule(
+        &mut rules,
+        &format!("ATTRS{{idVendor}}==\"{VENDOR_ID:04x}\""),
+    );
+    rules
+}
+
+/// Write the [`rules`] to `path`, usually [`RULES_PATH`], creating its directory if needed.
+/// They apply once udev reloads its rules, such as with
+/// `udevadm control --reload-rules && udevadm trigger`.
+pub fn install_rules(path: &Path) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, rules())
+}
+
+/// Is a Wooting device connected that this process isn't allowed to open? That is what keeps
+/// the SDK from finding it when the udev rules are missing.
+pub(crate) fn permission_denied() -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/hidraw") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let uevent = fs::read_to_string(entry.path().join("device/uevent")).unwrap_or_default();
+        let is_wooting = uevent_ids(&uevent)
+            .is_some_and(|(vendor_id, product_id)| usb::is_wooting(vendor_id, product_id));
+        is_wooting && !accessible(&Path::new("/dev").join(entry.file_name()))
+    })
+}
+
+/// Read the vendor and product ID from the `uevent` file of a HID device.
+fn uevent_ids(uevent: &str) -> Option<(u16, u16)> {
+    uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("HID_ID="))
+        .and_then(parse_hid_id)
+}
+
+/// Read the vendor and product ID from a HID ID such as `0003:000031E3:00001220`.
+fn parse_hid_id(id: &str) -> Option<(u16, u16)> {
+    let mut parts = id.split(':').skip(1);
+    let vendor_id = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let product_id = u32::from_str_radix(parts.next()?, 16).ok()?;
+    Some((vendor_id.try_into().ok()?, product_id.try_into().ok()?))
+}
+
+/// Can this process read from and write to the device at `path`?
+fn accessible(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let rules = super::rules();
+        let lines: Vec<_> = rules
+            .lines()
+            .filter(|line| line.starts_with("SUBSYSTEM"))
+            .collect();
+        // Through hidraw and as a USB device, for both original models and every other device.
+        assert_eq!(lines.len(), 6);
+        for line in &lines {
+            assert!(line.ends_with(r#", MODE="0660", TAG+="uaccess""#), "{line}");
+        }
+        assert!(lines.contains(
+            &r#"SUBSYSTEM=="hidraw", ATTRS{idVendor}=="31e3", MODE="0660", TAG+="uaccess""#
+        ));
+        assert!(lines.contains(&concat!(
+            r#"SUBSYSTEM=="usb", ATTRS{idVendor}=="03eb", ATTRS{idProduct}=="ff01", "#,
+            r#"MODE="0660", TAG+="uaccess""#
+        )));
+        assert!(lines.contains(&concat!(
+            r#"SUBSYSTEM=="usb", ATTRS{idVendor}=="03eb", ATTRS{idProduct}=="ff02", "#,
+            r#"MODE="0660", TAG+="uaccess""#
+        )));
+    }
+
+    #[test]
+    fn hid_ids() {
+        let uevent = "DRIVER=hid-generic\n\
+                      HID_ID=0003:000031E3:00001402\n\
+                      HID_NAME=Wooting Wooting 60HE\n\
+                      HID_PHYS=usb-0000:00:14.0-2/input0\n";
+        assert_eq!(uevent_ids(uevent), Some((0x31e3, 0x1402)));
+        assert_eq!(
+            parse_hid_id("0003:000003EB:0000FF01"),
+            Some((0x03eb, 0xff01))
+        );
+        assert_eq!(uevent_ids("DRIVER=hid-generic\nHID_NAME=Keyboard\n"), None);
+
+        for malformed in [
+            "",
+            "0003",
+            "0003:000031E3",
+            "0003::00001402",
+            "0003:WOOTING:00001402",
+            "0003:000031E3:-1",
+            // Too large for a USB ID.
+            "0003:000131E3:00001402",
+            "0003:000031E3:00011402",
+        ] {
+            assert_eq!(parse_hid_id(malformed), None, "{malformed}");
+        }
+    }
+}
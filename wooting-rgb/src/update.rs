@@ -0,0 +1,114 @@
+//! Choose how [`RgbKeyboard::array_update`](crate::RgbKeyboard::array_update) sends the color
+//! array, and measure how much it sends.
+//!
+//! The SDK sends the whole color array on every update, even when a single key changed. With
+//! [`UpdateStrategy::Partial`], the keyboard remembers the frame it last sent and writes only
+//! the keys that changed since, one at a time, as long as there are few of them. The SDK can't
+//! update part of the array, so every other update still sends all of it.
+//!
+//! ```rust,no_run
+//! use wooting_rgb::{update::UpdateStrategy, Key, RgbKeyboard};
+//!
+//! let mut keyboard = RgbKeyboard::open().unwrap();
+//! keyboard.set_update_strategy(UpdateStrategy::PARTIAL);
+//! for column in 0..21 {
+//!     keyboard.array_set_single((1, column), 255, 0, 0);
+//!     // Only the key that changed is sent.
+//!     keyboard.array_update();
+//! }
+//!
+//! let metrics = keyboard.update_metrics();
+//! println!("{:.1} bytes per frame", metrics.bytes_per_frame());
+//! ```
+
+use crate::{COLUMNS, COMPONENTS, ROWS};
+
+/// Bytes of color data in an update of the whole color array.
+pub const FULL_UPDATE_BYTES: usize = COMPONENTS * COLUMNS * ROWS;
+/// Bytes of color data in a direct write of a single key, its row and column followed by its
+/// color.
+pub const KEY_UPDATE_BYTES: usize = 2 + COMPONENTS;
+
+/// How the color array is sent to the keyboard.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum UpdateStrategy {
+    /// Always send the whole color array, like the SDK does.
+    #[default]
+    Full,
+    /// Write the keys that changed since the last update directly when there are at most
+    /// `max_keys` of them, and send the whole color array otherwise. Nothing is sent when no
+    /// key changed.
+    ///
+    /// Every key is written in a transfer of its own, so even though a handful of keys is far
+    /// less data than the whole array, it takes longer to send as the number of keys grows.
+    Partial { max_keys: usize },
+}
+
+impl UpdateStrategy {
+    /// Write up to 8 changed keys directly.
+    pub const PARTIAL: Self = Self::Partial { max_keys: 8 };
+}
+
+/// How many frames [`RgbKeyboard::array_update`](crate::RgbKeyboard::array_update) sent, and
+/// how much color data that took. Updates sent by the auto-update trigger count as well, but
+/// colors set with `direct_set_key` don't.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct UpdateMetrics {
+    /// Frames sent as the whole color array.
+    pub full_updates: u64,
+    /// Frames sent as direct writes of the keys that changed.
+    pub partial_updates: u64,
+    /// Frames that weren't sent because no key changed.
+    pub skipped_updates: u64,
+    /// Bytes of color data sent for every frame together.
+    pub bytes_sent: u64,
+    /// Bytes of color data sent for the last frame.
+    pub last_frame_bytes: usize,
+}
+
+impl UpdateMetrics {
+    /// How many frames were updated, whether anything was sent for them or not.
+    pub fn frames(&self) -> u64 {
+        self.full_updates + self.partial_updates + self.skipped_updates
+    }
+
+    /// The average bytes of color data sent per frame, or `0.0` before the first frame.
+    ///
+    /// ```rust
+    /// use wooting_rgb::update::{UpdateMetrics, FULL_UPDATE_BYTES, KEY_UPDATE_BYTES};
+    ///
+    /// let mut metrics = UpdateMetrics::default();
+    /// assert_eq!(metrics.bytes_per_frame(), 0.0);
+    ///
+    /// metrics.full_updates = 1;
+    /// metrics.partial_updates = 1;
+    /// metrics.bytes_sent = (FULL_UPDATE_BYTES + KEY_UPDATE_BYTES) as u64;
+    /// assert_eq!(metrics.bytes_per_frame(), 191.5);
+    /// ```
+    pub fn bytes_per_frame(&self) -> f64 {
+        match self.frames() {
+            0 => 0.0,
+            frames => self.bytes_sent as f64 / frames as f64,
+        }
+    }
+
+    /// Count a frame for which `keys` were written directly, or the whole color array was sent
+    /// if there are none.
+    pub(crate) fn record(&mut self, keys: Option<usize>) {
+        self.last_frame_bytes = match keys {
+            None => {
+                self.full_updates += 1;
+                FULL_UPDATE_BYTES
+            }
+            Some(0) => {
+                self.skipped_updates += 1;
+                0
+            }
+            Some(keys) => {
+                self.partial_updates += 1;
+                keys * KEY_UPDATE_BYTES
+            }
+        };
+        self.bytes_sent += self.last_frame_bytes as u64;
+    }
+}
@@ -0,0 +1,25 @@
+//! The USB vendor and product IDs Wooting devices identify themselves with.
+
+/// The vendor ID of every current Wooting device.
+pub const VENDOR_ID: u16 = 0x31e3;
+
+/// The vendor ID the original Wooting One and Two used, which belongs to their microcontroller.
+pub const LEGACY_VENDOR_ID: u16 = 0x03eb;
+
+/// The product IDs of the original Wooting One and Two, under [`LEGACY_VENDOR_ID`].
+pub const LEGACY_PRODUCT_IDS: [u16; 2] = [0xff01, 0xff02];
+
+/// Is a USB device with these IDs made by Wooting?
+///
+/// ```rust
+/// use wooting_rgb::usb;
+///
+/// // A Wooting Two HE.
+/// assert!(usb::is_wooting(0x31e3, 0x1220));
+/// // Another device with the same microcontroller as the original Wooting One.
+/// assert!(!usb::is_wooting(0x03eb, 0x2ff4));
+/// ```
+pub fn is_wooting(vendor_id: u16, product_id: u16) -> bool {
+    vendor_id == VENDOR_ID
+        || (vendor_id == LEGACY_VENDOR_ID && LEGACY_PRODUCT_IDS.contains(&product_id))
+}